@@ -0,0 +1,98 @@
+/// The number of bits per expected digest, giving a false positive rate of about 1%.
+const BITS_PER_DIGEST: usize = 10;
+
+/// The number of bits set for each digest.
+const HASHES: u64 = 7;
+
+/// A bloom filter over 64-bit digests, see `SourceMap::with_content_filter`.
+///
+/// The filter never returns a false negative, but returns a false positive for about 1% of
+/// absent digests while it holds at most its capacity. Digests can not be removed.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    capacity: usize,
+    len: usize,
+}
+
+impl BloomFilter {
+    /// Create an empty filter sized for the given number of digests.
+    pub fn new(capacity: usize) -> BloomFilter {
+        let capacity = capacity.max(1);
+        let words = (capacity * BITS_PER_DIGEST).div_ceil(64);
+
+        BloomFilter {
+            bits: vec![0; words],
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// Insert the given digest.
+    pub fn insert(&mut self, digest: u64) {
+        for bit in self.bits_of(digest) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+
+        self.len += 1;
+    }
+
+    /// Check if the given digest may have been inserted.
+    ///
+    /// If `false` is returned, the digest was never inserted.
+    pub fn maybe_contains(&self, digest: u64) -> bool {
+        self.bits_of(digest)
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Check if the filter holds as many digests as it is sized for.
+    ///
+    /// Inserting into a full filter raises its false positive rate.
+    pub fn is_full(&self) -> bool {
+        self.len >= self.capacity
+    }
+
+    fn bits_of(&self, digest: u64) -> impl Iterator<Item = usize> {
+        let size = (self.bits.len() * 64) as u64;
+        // double hashing, the second hash is a mix of the digest, and is odd so it is never 0.
+        let second = mix(digest) | 1;
+
+        (0..HASHES)
+            .map(move |index| (digest.wrapping_add(index.wrapping_mul(second)) % size) as usize)
+    }
+}
+
+/// The finalizer of splitmix64.
+fn mix(mut value: u64) -> u64 {
+    value ^= value >> 30;
+    value = value.wrapping_mul(0xbf58476d1ce4e5b9);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x94d049bb133111eb);
+
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(1000);
+        assert!(!filter.is_full());
+
+        for digest in 0..1000 {
+            filter.insert(mix(digest));
+        }
+
+        assert!(filter.is_full());
+        assert!((0..1000).all(|digest| filter.maybe_contains(mix(digest))));
+
+        let false_positives = (1000..11000)
+            .filter(|digest| filter.maybe_contains(mix(*digest)))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+
+        assert!(!BloomFilter::new(0).maybe_contains(1));
+    }
+}
@@ -0,0 +1,132 @@
+//! An integration with `codespan-reporting`, so source maps can be used as its file database.
+
+use std::ops::Range;
+
+use codespan_reporting::files::Error;
+use codespan_reporting::files::Files;
+
+use crate::source::Source;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// A source map is a file database, identifying files by the identifiers of their sources.
+///
+/// Lines are looked up through the line index of each source, see `Source::line_index`.
+///
+/// Example:
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::Diagnostic;
+/// use codespan_reporting::diagnostic::Label;
+/// use codespan_reporting::files::Files;
+/// use codespan_reporting::term;
+/// use codespan_reporting::term::termcolor::NoColor;
+///
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+/// use ara_source::SourceMap;
+///
+/// let mut map = SourceMap::new(vec![]);
+/// let id = map.add(Source::new(SourceKind::Script, "main.ara", "function main(): void {\n}\n")).unwrap();
+///
+/// assert_eq!(map.location(id, 9).unwrap().column_number, 10);
+///
+/// let diagnostic = Diagnostic::error()
+///     .with_message("unused function")
+///     .with_labels(vec![Label::primary(id, 9..13)]);
+///
+/// let mut output = NoColor::new(vec![]);
+/// term::emit(&mut output, &term::Config::default(), &map, &diagnostic).unwrap();
+///
+/// assert!(String::from_utf8(output.into_inner()).unwrap().contains("main.ara:1:10"));
+/// ```
+impl<'a> Files<'a> for SourceMap {
+    type FileId = SourceId;
+    type Name = &'a str;
+    type Source = &'a str;
+
+    fn name(&'a self, id: SourceId) -> Result<&'a str, Error> {
+        Ok(file(self, id)?.name())
+    }
+
+    fn source(&'a self, id: SourceId) -> Result<&'a str, Error> {
+        Ok(&file(self, id)?.content)
+    }
+
+    fn line_index(&'a self, id: SourceId, byte_index: usize) -> Result<usize, Error> {
+        let source = file(self, id)?;
+
+        source
+            .line_index()
+            .offset_to_line_col(byte_index)
+            .map(|(line, _)| line - 1)
+            .ok_or(Error::IndexTooLarge {
+                given: byte_index,
+                max: source.content.len(),
+            })
+    }
+
+    fn line_range(&'a self, id: SourceId, line_index: usize) -> Result<Range<usize>, Error> {
+        let source = file(self, id)?;
+        let index = source.line_index();
+
+        // codespan lines are 0-based, and include their line ending.
+        let start = index
+            .line_range(line_index + 1)
+            .ok_or(Error::LineTooLarge {
+                given: line_index,
+                max: index.line_count() - 1,
+            })?
+            .start;
+        let end = index
+            .line_range(line_index + 2)
+            .map_or(source.content.len(), |range| range.start);
+
+        Ok(start..end)
+    }
+}
+
+fn file(map: &SourceMap, id: SourceId) -> Result<&Source, Error> {
+    map.source(id).ok_or(Error::FileMissing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_files() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "a\r\nbc\n"),
+            Source::inline(SourceKind::Script, ""),
+        ]);
+        let ids = map.ids().collect::<Vec<_>>();
+
+        assert_eq!(map.name(ids[0]).unwrap(), "foo.ara");
+        assert_eq!(Files::source(&map, ids[1]).unwrap(), "");
+        assert_eq!(map.line_index(ids[0], 4).unwrap(), 1);
+        assert_eq!(map.line_index(ids[0], 6).unwrap(), 2);
+        assert_eq!(map.line_range(ids[0], 0).unwrap(), 0..3);
+        assert_eq!(map.line_range(ids[0], 1).unwrap(), 3..6);
+        assert_eq!(map.line_range(ids[0], 2).unwrap(), 6..6);
+        assert_eq!(map.line_range(ids[1], 0).unwrap(), 0..0);
+
+        let location = map.location(ids[0], 4).unwrap();
+        assert_eq!(location.line_number, 2);
+        assert_eq!(location.column_number, 2);
+
+        assert!(matches!(
+            map.line_index(ids[0], 7),
+            Err(Error::IndexTooLarge { given: 7, max: 6 })
+        ));
+        assert!(matches!(
+            map.line_range(ids[0], 3),
+            Err(Error::LineTooLarge { given: 3, max: 2 })
+        ));
+
+        map.remove(ids[1]);
+        assert!(matches!(map.name(ids[1]), Err(Error::FileMissing)));
+    }
+}
@@ -0,0 +1,91 @@
+//! An integration with `miette`, so sources can be attached to diagnostics as is.
+
+use miette::MietteError;
+use miette::MietteSpanContents;
+use miette::SourceCode;
+use miette::SourceSpan;
+use miette::SpanContents;
+
+use crate::error::Error;
+use crate::source::Source;
+use crate::SourceMap;
+
+/// A source is the source code of diagnostics about it, named after `Source::name`.
+///
+/// Example:
+///
+/// ```rust
+/// use miette::SourceCode;
+///
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+///
+/// let source = Source::new(SourceKind::Script, "main.ara", "function main(): void {\n}\n");
+/// let contents = source.read_span(&(9..13).into(), 0, 0).unwrap();
+///
+/// assert_eq!(contents.name(), Some("main.ara"));
+/// assert_eq!(contents.column(), 9);
+/// assert_eq!(contents.data(), b"main");
+/// ```
+impl SourceCode for Source {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents =
+            self.content
+                .as_str()
+                .read_span(span, context_lines_before, context_lines_after)?;
+
+        Ok(Box::new(MietteSpanContents::new_named(
+            self.name().to_string(),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+impl SourceMap {
+    /// Get an owned copy of the source with the given origin, to attach to a diagnostic.
+    ///
+    /// If the source is not found, `Error::SourceNotFound` is returned.
+    pub fn source_code<T: AsRef<str>>(&self, name: T) -> Result<Source, Error> {
+        self.named(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_source_code() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "a\nb\nc\nd"),
+            Source::inline(SourceKind::Script, "e"),
+        ]);
+
+        let source = map.source_code("foo.ara").unwrap();
+        let contents = source.read_span(&(4..5).into(), 1, 1).unwrap();
+        assert_eq!(contents.name(), Some("foo.ara"));
+        assert_eq!(contents.data(), b"b\nc\nd");
+        assert_eq!(contents.line(), 1);
+        assert_eq!(contents.line_count(), 3);
+
+        let contents = map.sources()[1].read_span(&(0..1).into(), 0, 0).unwrap();
+        assert_eq!(contents.name(), Some("<unknown>"));
+
+        assert!(source.read_span(&(6..20).into(), 0, 0).is_err());
+        assert!(matches!(
+            map.source_code("bar.ara"),
+            Err(Error::SourceNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use crate::error::Error;
+use crate::loader::SourceLoader;
+use crate::source::Source;
+use crate::source::SourceKind;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// The entry of an origin in a source map, see `SourceMap::entry`.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::loader::FileSourceLoader;
+/// use ara_source::source::SourceKind;
+/// use ara_source::SourceMap;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+/// let loader = FileSourceLoader::new(&root);
+///
+/// let mut map = SourceMap::new(vec![]);
+///
+/// let id = map.entry("src/main.ara").or_load_with(&loader).unwrap();
+/// assert_eq!(map.entry("src/main.ara").or_load_with(&loader).unwrap(), id);
+///
+/// let id = map.entry("foo.ara").or_insert_inline(SourceKind::Script, "");
+/// assert_eq!(map.entry("foo.ara").id(), Some(id));
+/// assert_eq!(map.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct SourceEntry<'a> {
+    map: &'a mut SourceMap,
+    origin: String,
+}
+
+impl<'a> SourceEntry<'a> {
+    pub(crate) fn new(map: &'a mut SourceMap, origin: String) -> SourceEntry<'a> {
+        SourceEntry { map, origin }
+    }
+
+    /// Get the origin of this entry.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+
+    /// Get the identifier of the source with the origin of this entry, if any.
+    pub fn id(&mut self) -> Option<SourceId> {
+        self.map.id_of(&self.origin)
+    }
+
+    /// Get the identifier of the source with the origin of this entry, adding a source with
+    /// the given kind and content if there is none.
+    pub fn or_insert_inline<T: Into<String>>(mut self, kind: SourceKind, content: T) -> SourceId {
+        match self.id() {
+            Some(id) => id,
+            None => self.map.push(Source::new(kind, self.origin, content)),
+        }
+    }
+
+    /// Get the identifier of the source with the origin of this entry, loading it with the
+    /// given loader if there is none.
+    ///
+    /// The origin is passed to the loader as the name to load, and only the loaded source with
+    /// the same origin is added, if the loader does not produce it, `Error::SourceNotFound` is
+    /// returned, and the source map is left unchanged.
+    pub fn or_load_with<L: SourceLoader + ?Sized>(mut self, loader: &L) -> Result<SourceId, Error> {
+        if let Some(id) = self.id() {
+            return Ok(id);
+        }
+
+        let loaded = loader.load(Path::new(&self.origin))?;
+        let source = loaded
+            .into_iter()
+            .find(|source| source.origin.as_deref() == Some(self.origin.as_str()))
+            .ok_or_else(|| Error::SourceNotFound(self.origin.clone()))?;
+
+        Ok(self.map.push(source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::DirectorySourceLoader;
+    use crate::loader::FileSourceLoader;
+
+    #[test]
+    fn test_entry() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let mut map = SourceMap::single(Source::new(SourceKind::Script, "foo.ara", "foo"));
+
+        let foo = map
+            .entry("foo.ara")
+            .or_insert_inline(SourceKind::Script, "bar");
+        assert_eq!(map.len(), 1);
+        assert_eq!(map[foo].content, "foo");
+
+        let loader = FileSourceLoader::new(&root);
+        let main = map.entry("src/main.ara").or_load_with(&loader).unwrap();
+        assert_eq!(map[main].kind, SourceKind::Script);
+        assert_eq!(map.entry("src/main.ara").id(), Some(main));
+
+        for source in map.iter_mut() {
+            source.origin = Some("bar.ara".to_string());
+        }
+        assert_eq!(map.entry("bar.ara").id(), Some(foo));
+        assert_eq!(map.entry("foo.ara").id(), None);
+
+        assert!(map.entry("src/missing.ara").or_load_with(&loader).is_err());
+        assert!(map
+            .entry("vendor")
+            .or_load_with(&DirectorySourceLoader::new(&root))
+            .is_err());
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -1,7 +1,21 @@
+use std::path::PathBuf;
+
+/// The errors of loading and looking up sources.
+///
+/// New variants may be added in any minor release, so matches must have a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     SourceNotFound(String),
     InvalidSource(String),
+    /// A source with the given origin already exists in the source map.
+    DuplicateSource(String),
+    /// The source was found while scanning, but was removed before it could be read.
+    SourceVanished(PathBuf),
+    /// The path of the source is not valid UTF-8, and cannot be used as an origin.
+    NonUtf8Path(PathBuf),
+    /// The content of the source with the given origin was rejected by a validator.
+    InvalidContent(String, String),
     IoError(std::io::Error),
 }
 
@@ -16,6 +30,20 @@ impl std::fmt::Display for Error {
         match self {
             Error::SourceNotFound(name) => write!(f, "source `{name}` not found."),
             Error::InvalidSource(message) => write!(f, "invalid source: {message}"),
+            Error::DuplicateSource(origin) => write!(f, "source `{origin}` already exists."),
+            Error::SourceVanished(path) => write!(
+                f,
+                "source `{}` vanished before it could be read.",
+                path.to_string_lossy()
+            ),
+            Error::NonUtf8Path(path) => write!(
+                f,
+                "source path `{}` is not valid UTF-8.",
+                path.to_string_lossy()
+            ),
+            Error::InvalidContent(origin, message) => {
+                write!(f, "invalid content in source `{origin}`: {message}")
+            }
             Error::IoError(error) => write!(f, "io error: {error}"),
         }
     }
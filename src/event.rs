@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::loader::InclusionDecision;
+use crate::report::SkipReason;
+
+/// A decision taken by a loader about a path.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LoaderEvent {
+    /// The file was loaded.
+    Loaded(PathBuf),
+
+    /// The path was not loaded, see `InclusionDecision`.
+    Excluded(PathBuf, InclusionDecision),
+
+    /// The path was skipped, see `SkipReason`.
+    Skipped(PathBuf, SkipReason),
+}
+
+/// An in-memory ring buffer of loader events.
+///
+/// Once the capacity is reached, the oldest events are dropped.
+#[derive(Debug)]
+pub struct EventLog {
+    capacity: usize,
+    events: Mutex<VecDeque<LoaderEvent>>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> EventLog {
+        EventLog {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Record the given event, dropping the oldest event if the log is full.
+    pub fn record(&self, event: LoaderEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+
+        events.push_back(event);
+    }
+
+    /// Get the recorded events, from the oldest to the newest.
+    pub fn events(&self) -> Vec<LoaderEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Remove all recorded events.
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log() {
+        let log = EventLog::new(2);
+
+        log.record(LoaderEvent::Loaded(PathBuf::from("a.ara")));
+        log.record(LoaderEvent::Loaded(PathBuf::from("b.ara")));
+        log.record(LoaderEvent::Excluded(
+            PathBuf::from("c.txt"),
+            InclusionDecision::UnsupportedExtension,
+        ));
+
+        assert_eq!(
+            log.events(),
+            vec![
+                LoaderEvent::Loaded(PathBuf::from("b.ara")),
+                LoaderEvent::Excluded(
+                    PathBuf::from("c.txt"),
+                    InclusionDecision::UnsupportedExtension
+                ),
+            ]
+        );
+
+        log.clear();
+        assert!(log.events().is_empty());
+
+        let log = EventLog::new(0);
+        log.record(LoaderEvent::Loaded(PathBuf::from("a.ara")));
+        assert!(log.events().is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+//! A C ABI over source maps, for tooling that is not written in Rust.
+//!
+//! Source maps are created with `ara_source_map_load_directories`, and must be freed with
+//! `ara_source_map_free`. Sources are queried by their zero-based position, and every
+//! returned string is owned by the caller, and must be freed with `ara_source_string_free`.
+//!
+//! The library can be built for C consumers with `cargo rustc --features ffi --crate-type cdylib`.
+
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::sync::Arc;
+
+use crate::loader::load_directories;
+use crate::source::Source;
+use crate::source::SourceKind;
+use crate::SourceMap;
+
+/// The kind returned by `ara_source_map_kind` for scripts.
+pub const ARA_SOURCE_KIND_SCRIPT: i32 = 0;
+
+/// The kind returned by `ara_source_map_kind` for definitions.
+pub const ARA_SOURCE_KIND_DEFINITION: i32 = 1;
+
+/// The kind returned by `ara_source_map_kind` for out of range positions.
+pub const ARA_SOURCE_KIND_INVALID: i32 = -1;
+
+/// Load a source map from the given directories of the given root, see `load_directories`.
+///
+/// Returns null if any of the paths is null or not valid UTF-8, or if loading fails.
+///
+/// # Safety
+///
+/// `root` must be a valid NUL-terminated string, and `directories` must point to
+/// `count` valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_load_directories(
+    root: *const c_char,
+    directories: *const *const c_char,
+    count: usize,
+) -> *mut SourceMap {
+    let root = match string(root) {
+        Some(root) => root,
+        None => return std::ptr::null_mut(),
+    };
+
+    let directories = match count {
+        0 => Some(vec![]),
+        _ if directories.is_null() => None,
+        _ => std::slice::from_raw_parts(directories, count)
+            .iter()
+            .map(|directory| string(*directory))
+            .collect::<Option<Vec<&str>>>(),
+    };
+
+    match directories.map(|directories| load_directories(root, directories)) {
+        Some(Ok(map)) => Box::into_raw(Box::new(map)),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Free a source map returned by `ara_source_map_load_directories`.
+///
+/// # Safety
+///
+/// `map` must be null, or a source map returned by this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_free(map: *mut SourceMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map));
+    }
+}
+
+/// Get the number of sources in the given source map, 0 if it is null.
+///
+/// # Safety
+///
+/// `map` must be null, or a valid source map returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_len(map: *const SourceMap) -> usize {
+    map.as_ref().map_or(0, |map| map.len())
+}
+
+/// Get the kind of the source at the given position, or `ARA_SOURCE_KIND_INVALID`.
+///
+/// # Safety
+///
+/// `map` must be null, or a valid source map returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_kind(map: *const SourceMap, position: usize) -> i32 {
+    match source(map, position).map(|source| source.kind) {
+        Some(SourceKind::Script) => ARA_SOURCE_KIND_SCRIPT,
+        Some(SourceKind::Definition) => ARA_SOURCE_KIND_DEFINITION,
+        None => ARA_SOURCE_KIND_INVALID,
+    }
+}
+
+/// Get a copy of the origin of the source at the given position.
+///
+/// Returns null if the position is out of range, or if the source has no origin.
+///
+/// # Safety
+///
+/// `map` must be null, or a valid source map returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_origin(
+    map: *const SourceMap,
+    position: usize,
+) -> *mut c_char {
+    owned(source(map, position).and_then(|source| source.origin.as_deref()))
+}
+
+/// Get a copy of the content of the source at the given position.
+///
+/// Returns null if the position is out of range, or if the content contains a NUL byte.
+///
+/// # Safety
+///
+/// `map` must be null, or a valid source map returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_content(
+    map: *const SourceMap,
+    position: usize,
+) -> *mut c_char {
+    owned(source(map, position).map(|source| source.content.as_str()))
+}
+
+/// Get the fingerprint of the source at the given position, see `Source::fingerprint`.
+///
+/// Returns 0 if the position is out of range.
+///
+/// # Safety
+///
+/// `map` must be null, or a valid source map returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_map_fingerprint(map: *const SourceMap, position: usize) -> u64 {
+    source(map, position).map_or(0, |source| source.fingerprint())
+}
+
+/// Free a string returned by this library.
+///
+/// # Safety
+///
+/// `string` must be null, or a string returned by this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn ara_source_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn string<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(string).to_str().ok()
+}
+
+unsafe fn source<'a>(map: *const SourceMap, position: usize) -> Option<&'a Source> {
+    map.as_ref()?.sources().get(position).map(Arc::as_ref)
+}
+
+fn owned(string: Option<&str>) -> *mut c_char {
+    string
+        .and_then(|string| CString::new(string).ok())
+        .map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        let root = CString::new(format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        ))
+        .unwrap();
+        let directories = [
+            CString::new("src").unwrap(),
+            CString::new("vendor").unwrap(),
+        ];
+        let pointers = directories
+            .iter()
+            .map(|directory| directory.as_ptr())
+            .collect::<Vec<_>>();
+
+        unsafe {
+            let map = ara_source_map_load_directories(root.as_ptr(), pointers.as_ptr(), 2);
+            assert!(!map.is_null());
+            assert_eq!(ara_source_map_len(map), 3);
+
+            assert_eq!(ara_source_map_kind(map, 0), ARA_SOURCE_KIND_DEFINITION);
+            assert_eq!(ara_source_map_kind(map, 2), ARA_SOURCE_KIND_SCRIPT);
+            assert_eq!(ara_source_map_kind(map, 3), ARA_SOURCE_KIND_INVALID);
+
+            let origin = ara_source_map_origin(map, 2);
+            assert_eq!(CStr::from_ptr(origin).to_str().unwrap(), "src/main.ara");
+            ara_source_string_free(origin);
+
+            let content = ara_source_map_content(map, 2);
+            assert_eq!(
+                CStr::from_ptr(content).to_str().unwrap(),
+                (*map).sources()[2].content
+            );
+            ara_source_string_free(content);
+
+            assert_eq!(
+                ara_source_map_fingerprint(map, 2),
+                (*map).sources()[2].fingerprint()
+            );
+            assert!(ara_source_map_origin(map, 3).is_null());
+
+            ara_source_map_free(map);
+
+            let missing = [CString::new("missing").unwrap()];
+            let pointers = [missing[0].as_ptr()];
+            assert!(ara_source_map_load_directories(root.as_ptr(), pointers.as_ptr(), 1).is_null());
+            assert!(
+                ara_source_map_load_directories(std::ptr::null(), std::ptr::null(), 0).is_null()
+            );
+            assert_eq!(ara_source_map_len(std::ptr::null()), 0);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::path::MAIN_SEPARATOR;
+
+/// A compiled glob pattern, matched against `/`-separated paths.
+///
+/// The following syntax is supported:
+///
+/// - `?` matches any single character, except a separator.
+/// - `*` matches any sequence of characters, except a separator.
+/// - `**` as a whole segment matches any number of directories.
+/// - `[abc]`, `[a-z]`, and `[!a-z]` match a single character from ( or not from ) a class.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::glob::Glob;
+///
+/// let glob = Glob::new("vendor/**/*.d.ara");
+///
+/// assert!(glob.matches("vendor/foo/write_line.d.ara"));
+/// assert!(glob.matches("vendor/bar.d.ara"));
+/// assert!(!glob.matches("src/main.ara"));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Glob {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Segment {
+    AnyDirectories,
+    Pattern(Vec<Token>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyChars,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compile the given pattern.
+    ///
+    /// A leading `/` is ignored, and an unterminated `[` is matched literally.
+    pub fn new<T: Into<String>>(pattern: T) -> Glob {
+        let pattern = pattern.into();
+        let segments = pattern
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                if segment == "**" {
+                    Segment::AnyDirectories
+                } else {
+                    Segment::Pattern(tokenize(segment))
+                }
+            })
+            .collect();
+
+        Glob { pattern, segments }
+    }
+
+    /// Get the pattern this glob was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Get the leading segments of the pattern that contain no wildcards.
+    ///
+    /// Every matching path starts with this prefix, so only this directory needs to be walked.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::glob::Glob;
+    ///
+    /// assert_eq!(Glob::new("vendor/*/*.d.ara").literal_prefix(), "vendor");
+    /// assert_eq!(Glob::new("src/foo/**/*.ara").literal_prefix(), "src/foo");
+    /// assert_eq!(Glob::new("**/*.ara").literal_prefix(), "");
+    /// ```
+    pub fn literal_prefix(&self) -> String {
+        let mut prefix = vec![];
+
+        // the last segment matches file names, so it is never part of the prefix.
+        for segment in self
+            .segments
+            .iter()
+            .take(self.segments.len().saturating_sub(1))
+        {
+            let literal = match segment {
+                Segment::AnyDirectories => None,
+                Segment::Pattern(tokens) => tokens
+                    .iter()
+                    .map(|token| match token {
+                        Token::Literal(c) => Some(*c),
+                        _ => None,
+                    })
+                    .collect::<Option<String>>(),
+            };
+
+            match literal {
+                Some(literal) => prefix.push(literal),
+                None => break,
+            }
+        }
+
+        prefix.join("/")
+    }
+
+    /// Check if the given path matches this glob.
+    ///
+    /// Both `/` and the platform separator are accepted as separators.
+    pub fn matches<T: AsRef<str>>(&self, path: T) -> bool {
+        let parts = path
+            .as_ref()
+            .split(['/', MAIN_SEPARATOR])
+            .filter(|part| !part.is_empty())
+            .map(|part| part.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+
+        match_segments(&self.segments, &parts)
+    }
+}
+
+fn tokenize(segment: &str) -> Vec<Token> {
+    let chars = segment.chars().collect::<Vec<char>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '?' => tokens.push(Token::AnyChar),
+            '*' => {
+                if tokens.last() != Some(&Token::AnyChars) {
+                    tokens.push(Token::AnyChars);
+                }
+            }
+            '[' => match tokenize_class(&chars[i + 1..]) {
+                Some((token, length)) => {
+                    tokens.push(token);
+                    i += length;
+                }
+                None => tokens.push(Token::Literal('[')),
+            },
+            c => tokens.push(Token::Literal(c)),
+        }
+
+        i += 1;
+    }
+
+    tokens
+}
+
+fn tokenize_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!') | Some('^'));
+    let mut i = usize::from(negated);
+    let mut ranges = vec![];
+
+    loop {
+        let start = *chars.get(i)?;
+        if start == ']' && !ranges.is_empty() {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|end| *end != ']') {
+            ranges.push((start, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((start, start));
+            i += 1;
+        }
+    }
+}
+
+// both matchers only backtrack to the last wildcard, which is enough since a later
+// wildcard can match anything an earlier one would have, keeping matching polynomial
+// instead of exponential in the number of wildcards.
+fn match_segments(segments: &[Segment], parts: &[Vec<char>]) -> bool {
+    let (mut s, mut p) = (0, 0);
+    // the position of the last `**`, and of the first part it has not matched yet.
+    let mut last_wildcard = None;
+
+    while p < parts.len() {
+        match segments.get(s) {
+            Some(Segment::AnyDirectories) => {
+                last_wildcard = Some((s, p));
+                s += 1;
+            }
+            Some(Segment::Pattern(tokens)) if match_tokens(tokens, &parts[p]) => {
+                s += 1;
+                p += 1;
+            }
+            _ => match last_wildcard {
+                // let the wildcard match one more part, and try again after it.
+                Some((wildcard, matched)) => {
+                    last_wildcard = Some((wildcard, matched + 1));
+                    s = wildcard + 1;
+                    p = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    segments[s..]
+        .iter()
+        .all(|segment| *segment == Segment::AnyDirectories)
+}
+
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    let (mut t, mut c) = (0, 0);
+    // the position of the last `*`, and of the first character it has not matched yet.
+    let mut last_wildcard = None;
+
+    while c < chars.len() {
+        match tokens.get(t) {
+            Some(Token::AnyChars) => {
+                last_wildcard = Some((t, c));
+                t += 1;
+            }
+            Some(token) if match_token(token, chars[c]) => {
+                t += 1;
+                c += 1;
+            }
+            _ => match last_wildcard {
+                // let the wildcard match one more character, and try again after it.
+                Some((wildcard, matched)) => {
+                    last_wildcard = Some((wildcard, matched + 1));
+                    t = wildcard + 1;
+                    c = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::AnyChars)
+}
+
+fn match_token(token: &Token, c: char) -> bool {
+    match token {
+        Token::Literal(literal) => *literal == c,
+        Token::AnyChar | Token::AnyChars => true,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|(start, end)| *start <= c && c <= *end) != *negated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let glob = Glob::new("src/**/*.ara");
+        assert!(glob.matches("src/main.ara"));
+        assert!(glob.matches("src/foo/bar/baz.ara"));
+        assert!(glob.matches("src/foo/bar.d.ara"));
+        assert!(!glob.matches("vendor/src/main.ara"));
+        assert!(!glob.matches("src/main.ara.txt"));
+
+        let glob = Glob::new("vendor/*/*.d.ara");
+        assert!(glob.matches("vendor/foo/write_line.d.ara"));
+        assert!(!glob.matches("vendor/write_line.d.ara"));
+        assert!(!glob.matches("vendor/foo/bar/write_line.d.ara"));
+
+        let glob = Glob::new("**/test?.ara");
+        assert!(glob.matches("test1.ara"));
+        assert!(glob.matches("a/b/testA.ara"));
+        assert!(!glob.matches("a/b/test10.ara"));
+
+        let glob = Glob::new("/[a-c]*.ara");
+        assert!(glob.matches("bar.ara"));
+        assert!(!glob.matches("foo.ara"));
+
+        let glob = Glob::new("[!a-c]*.ara");
+        assert!(!glob.matches("bar.ara"));
+        assert!(glob.matches("foo.ara"));
+
+        let glob = Glob::new("[unterminated.ara");
+        assert!(glob.matches("[unterminated.ara"));
+        assert_eq!(glob.as_str(), "[unterminated.ara");
+    }
+
+    #[test]
+    fn test_glob_backtracking() {
+        let glob = Glob::new("*a*b*.ara");
+        assert!(glob.matches("xaybzb.ara"));
+        assert!(glob.matches("ab.ara"));
+        assert!(!glob.matches("ba.ara"));
+
+        let glob = Glob::new("**/src/**/lib/*.ara");
+        assert!(glob.matches("a/src/b/src/lib/c.ara"));
+        assert!(glob.matches("src/lib/c.ara"));
+        assert!(!glob.matches("src/c/lib.ara"));
+
+        let glob = Glob::new("**/**");
+        assert!(glob.matches(""));
+        assert!(glob.matches("a/b"));
+
+        // patterns from ignore files must not take exponential time on long names.
+        let glob = Glob::new(format!("{}b", "*a".repeat(32)));
+        assert!(!glob.matches("a".repeat(64)));
+
+        let glob = Glob::new(format!("{}b", "**/a/".repeat(32)));
+        assert!(!glob.matches("a/".repeat(64)));
+    }
+}
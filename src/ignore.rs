@@ -0,0 +1,144 @@
+use crate::glob::Glob;
+
+/// The name of the ignore file read from the loader root, see `DirectorySourceLoader::with_araignore`.
+pub const ARA_IGNORE_FILE: &str = ".araignore";
+
+/// The name of the ignore files read while traversing, see `DirectorySourceLoader::with_gitignore`.
+pub const GIT_IGNORE_FILE: &str = ".gitignore";
+
+/// A single rule of an ignore file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IgnoreRule {
+    /// The glob matched against paths relative to the ignore file directory.
+    pub glob: Glob,
+
+    /// Whether the rule re-includes paths ignored by a previous rule ( `!pattern` ).
+    pub negated: bool,
+
+    /// Whether the rule only matches directories ( `pattern/` ).
+    pub directory_only: bool,
+}
+
+/// A set of gitignore-style rules.
+///
+/// The following syntax is supported:
+///
+/// - Blank lines and lines starting with `#` are ignored.
+/// - A pattern prefixed with `!` re-includes paths ignored by a previous pattern.
+/// - A pattern ending with `/` only matches directories.
+/// - A pattern containing a `/` ( other than a trailing one ) is relative to the ignore file
+///   directory, otherwise it matches at any depth.
+///
+/// The last matching rule wins, and paths within an ignored directory are always ignored.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::ignore::IgnoreRules;
+///
+/// let rules = IgnoreRules::parse("# generated\nbuild/\n*.gen.ara\n!keep.gen.ara\n/fixtures");
+///
+/// assert!(rules.is_ignored("build", true));
+/// assert!(rules.is_ignored("src/build/main.ara", false));
+/// assert!(rules.is_ignored("src/foo.gen.ara", false));
+/// assert!(rules.is_ignored("fixtures/foo.ara", false));
+/// assert!(!rules.is_ignored("src/keep.gen.ara", false));
+/// assert!(!rules.is_ignored("src/fixtures/foo.ara", false));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct IgnoreRules {
+    pub rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    /// Parse the content of an ignore file.
+    pub fn parse<T: AsRef<str>>(content: T) -> IgnoreRules {
+        let rules = content
+            .as_ref()
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, pattern) = match line.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+
+                let (directory_only, pattern) = match pattern.strip_suffix('/') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern),
+                };
+
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                let glob = if pattern.contains('/') {
+                    Glob::new(pattern)
+                } else {
+                    Glob::new(format!("**/{}", pattern))
+                };
+
+                Some(IgnoreRule {
+                    glob,
+                    negated,
+                    directory_only,
+                })
+            })
+            .collect();
+
+        IgnoreRules { rules }
+    }
+
+    /// Check if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Check if the given `/`-separated path, relative to the ignore file directory, is ignored.
+    pub fn is_ignored<T: AsRef<str>>(&self, path: T, is_directory: bool) -> bool {
+        let parts = path
+            .as_ref()
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+
+        (1..=parts.len()).any(|length| {
+            let is_directory = length < parts.len() || is_directory;
+
+            self.matches(&parts[..length].join("/"), is_directory) == Some(true)
+        })
+    }
+
+    /// Get whether the last rule matching the given path ignores it, if any rule matches.
+    pub(crate) fn matches(&self, path: &str, is_directory: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_directory || !rule.directory_only) && rule.glob.matches(path))
+            .map(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::parse("\n# comment\ntarget/\n!target/keep.ara\ndocs/*.ara\n");
+
+        assert_eq!(rules.rules.len(), 3);
+
+        assert!(rules.is_ignored("target", true));
+        assert!(!rules.is_ignored("target", false));
+        assert!(rules.is_ignored("target/keep.ara", false));
+        assert!(rules.is_ignored("vendor/target/foo.ara", false));
+
+        assert!(rules.is_ignored("docs/foo.ara", false));
+        assert!(!rules.is_ignored("docs/foo/bar.ara", false));
+        assert!(!rules.is_ignored("src/docs/foo.ara", false));
+
+        assert!(IgnoreRules::parse("# only comments\n/\n").is_empty());
+    }
+}
@@ -1,43 +1,880 @@
-use crate::error::Error;
-use crate::source::Source;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hasher;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use crate::bloom::BloomFilter;
+use crate::entry::SourceEntry;
+use crate::glob::Glob;
+use crate::loader::absolute_root;
+use crate::loader::read_error;
+use crate::loader::FileSourceLoader;
+use crate::loader::InclusionDecision;
+use crate::loader::SourceLoader;
+use crate::path::relative_to;
+use crate::path::verbatim_path;
+use crate::quarantine::Quarantine;
+use crate::report::SourceCounts;
+use crate::report::SourceMapReport;
+use crate::source::FileStamp;
+use crate::walk::WalkEntry;
+
+pub use crate::error::Error;
+pub use crate::source::Source;
+pub use crate::source::SourceKind;
+
+mod bloom;
+#[cfg(feature = "codespan")]
+pub mod codespan;
+#[cfg(feature = "miette")]
+pub mod diagnostic;
+pub mod entry;
 pub mod error;
+pub mod event;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod glob;
+pub mod ignore;
+pub mod line_index;
 pub mod loader;
+pub mod lsp;
+pub mod path;
+pub mod position;
+pub mod pragma;
+pub mod quarantine;
+pub mod remap;
+pub mod report;
+#[cfg(feature = "serde")]
+pub mod serialize;
 pub mod source;
+#[cfg(test)]
+mod temp_dir;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "unstable")]
+pub mod unstable;
+pub mod walk;
+
+/// The combined fingerprints of the sources of a source map, per kind.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KindFingerprints {
+    /// The combined fingerprint of all `SourceKind::Script` sources.
+    pub scripts: u64,
+
+    /// The combined fingerprint of all `SourceKind::Definition` sources.
+    pub definitions: u64,
+}
+
+/// The resolution of origin collisions when merging source maps, see `SourceMap::merge_with`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum MergeStrategy {
+    /// Keep the existing source, dropping the incoming one.
+    PreferExisting,
+
+    /// Replace the existing source with the incoming one.
+    PreferIncoming,
+
+    /// Fail with `Error::DuplicateSource`.
+    Error,
+}
+
+/// A record of a source removed from a source map, see `SourceMap::with_tombstones`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Tombstone {
+    /// The identifier of the removed source, which is never reused.
+    pub id: SourceId,
+
+    /// The origin of the removed source, if it had one.
+    pub origin: Option<String>,
+
+    /// The fingerprint of the removed source, see `Source::fingerprint`.
+    pub fingerprint: u64,
+}
+
+/// The identifier of a source within a source map.
+///
+/// Identifiers are returned when sources are added to a source map, they are never reused,
+/// and stay valid until their source is removed, even when the sources are reordered.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct SourceId(usize);
+
+impl SourceId {
+    /// Get the zero-based number of the identifier, in order of addition to its source map.
+    ///
+    /// This is not the position of the source, which changes when sources are reordered or removed.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl std::fmt::Display for SourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// An iterator over the sources of a source map, see `SourceMap::iter`.
+pub type Iter<'a> = std::iter::Map<std::slice::Iter<'a, Arc<Source>>, fn(&Arc<Source>) -> &Source>;
+
+/// A mutable iterator over the sources of a source map, see `SourceMap::iter_mut`.
+pub type IterMut<'a> =
+    std::iter::Map<std::slice::IterMut<'a, Arc<Source>>, fn(&mut Arc<Source>) -> &mut Source>;
+
+/// An owning iterator over the sources of a source map.
+pub type IntoIter = std::iter::Map<std::vec::IntoIter<Arc<Source>>, fn(Arc<Source>) -> Source>;
 
 #[derive(Debug)]
 pub struct SourceMap {
-    pub sources: Vec<Source>,
+    // sources are shared with the maps derived from this one, and cloned on write.
+    sources: Vec<Arc<Source>>,
+    // the identifier of the source at each position.
+    ids: Vec<SourceId>,
+    // the position of the source of each identifier, removed sources have none, so the
+    // positions do not grow as sources are removed and added again.
+    positions: HashMap<SourceId, usize>,
+    // the number of the next identifier, identifiers are never reused.
+    next_id: usize,
+    // the first source of each origin, built lazily, and reset when origins may have been
+    // changed through mutable access.
+    origins: OnceLock<HashMap<Arc<str>, SourceId>>,
+    // the number of sources per kind, maintained as sources are added and removed, and
+    // reset like the origins.
+    counts: OnceLock<SourceCounts>,
+    packages: Vec<(String, String)>,
+    quarantined: HashMap<String, Quarantine>,
+    // the tombstones of removed sources, `None` if they are not kept.
+    tombstones: Option<Vec<Tombstone>>,
+    // whether `refresh` removes sources whose file was removed, instead of failing.
+    remove_vanished: bool,
+    // the errors attached to each source, see `attach_error`.
+    errors: HashMap<SourceId, Vec<Error>>,
+    // a filter over the content digests of the sources, `None` if it is not kept, built
+    // lazily like the origins.
+    content_filter: Option<OnceLock<BloomFilter>>,
 }
 
 impl SourceMap {
+    /// Create a source map from the given sources.
+    ///
+    /// Sources are not checked for duplicate origins, use `add_all` to reject them.
     pub fn new(sources: Vec<Source>) -> SourceMap {
-        SourceMap { sources }
+        let mut map = SourceMap {
+            sources: vec![],
+            ids: vec![],
+            positions: HashMap::new(),
+            next_id: 0,
+            origins: OnceLock::from(HashMap::new()),
+            counts: OnceLock::from(SourceCounts::default()),
+            packages: vec![],
+            quarantined: HashMap::new(),
+            tombstones: None,
+            remove_vanished: false,
+            errors: HashMap::new(),
+            content_filter: None,
+        };
+
+        map.push_all(sources);
+
+        map
+    }
+
+    /// Load a source map from the entries of an existing directory walker.
+    ///
+    /// Entries are loaded by a `FileSourceLoader` of the given root, entries it does not
+    /// support ( e.g. directories, or files with other extensions ) are skipped.
+    ///
+    /// Definitions are placed before scripts.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use std::path::PathBuf;
+    ///
+    /// use ara_source::SourceMap;
+    ///
+    /// let root = PathBuf::from(format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR")));
+    /// let entries = vec![
+    ///     root.join("src/main.ara"),
+    ///     root.join("vendor/foo"),
+    ///     root.join("vendor/foo/write_line.d.ara"),
+    /// ];
+    ///
+    /// let map = SourceMap::from_walk(&root, entries).unwrap();
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.sources()[0].name(), "vendor/foo/write_line.d.ara");
+    /// ```
+    pub fn from_walk<T, I, E>(root: &T, entries: I) -> Result<SourceMap, Error>
+    where
+        T: AsRef<Path>,
+        I: IntoIterator<Item = E>,
+        E: WalkEntry,
+    {
+        let loader = FileSourceLoader::new(root);
+        let mut map = SourceMap::new(vec![]);
+
+        for entry in entries {
+            let path = entry.path();
+
+            if entry.is_file() && matches!(loader.explain(&path), InclusionDecision::Included(_)) {
+                loader.load_into(path, &mut map)?;
+            }
+        }
+
+        map.sort_definitions_first();
+
+        Ok(map)
+    }
+
+    /// Keep a tombstone of each removed source, until it is acknowledged.
+    ///
+    /// This lets incremental consumers resolve diagnostics referring to removed sources,
+    /// see `tombstones` and `acknowledge_tombstones`. This is disabled by default.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]).with_tombstones(true);
+    /// let foo = map.add(Source::new(SourceKind::Script, "foo.ara", "")).unwrap();
+    ///
+    /// map.remove(foo);
+    ///
+    /// assert_eq!(map.tombstone(foo).unwrap().origin.as_deref(), Some("foo.ara"));
+    /// assert_eq!(map.acknowledge_tombstones().len(), 1);
+    /// assert!(map.tombstones().is_empty());
+    /// ```
+    pub fn with_tombstones(mut self, tombstones: bool) -> SourceMap {
+        self.tombstones = if tombstones {
+            Some(self.tombstones.unwrap_or_default())
+        } else {
+            None
+        };
+
+        self
+    }
+
+    /// Remove sources whose file was removed when refreshing, instead of failing with
+    /// `Error::SourceVanished`, see `refresh`. This is disabled by default.
+    ///
+    /// Removed sources leave a tombstone if tombstones are kept, see `with_tombstones`.
+    pub fn with_remove_vanished(mut self, remove_vanished: bool) -> SourceMap {
+        self.remove_vanished = remove_vanished;
+
+        self
+    }
+
+    /// Create a source map containing a single source.
+    ///
+    /// No index is built: the index of origins and the content filter are only built if
+    /// they are used, so single-file tooling that only reads the source does not pay for them.
+    pub fn single(source: Source) -> SourceMap {
+        SourceMap {
+            sources: vec![Arc::new(source)],
+            ids: vec![SourceId(0)],
+            positions: HashMap::from([(SourceId(0), 0)]),
+            next_id: 1,
+            origins: OnceLock::new(),
+            counts: OnceLock::new(),
+            packages: vec![],
+            quarantined: HashMap::new(),
+            tombstones: None,
+            remove_vanished: false,
+            errors: HashMap::new(),
+            content_filter: None,
+        }
+    }
+
+    /// Add the given source to the source map, returning its identifier.
+    ///
+    /// If a source with the same origin exists, `Error::DuplicateSource` is returned.
+    pub fn add(&mut self, source: Source) -> Result<SourceId, Error> {
+        self.check_duplicates(std::iter::once(&source))?;
+
+        Ok(self.push(source))
+    }
+
+    /// Add all the given sources to the source map, returning their identifiers.
+    ///
+    /// The storage is grown once for all sources, which is faster than repeated `add` calls.
+    ///
+    /// If any of the sources has the same origin as an existing source, or as another one of
+    /// the given sources, `Error::DuplicateSource` is returned, and no source is added.
+    pub fn add_all(&mut self, sources: Vec<Source>) -> Result<Vec<SourceId>, Error> {
+        self.check_duplicates(sources.iter())?;
+
+        Ok(self.push_all(sources))
     }
 
-    pub fn add(&mut self, source: Source) {
+    fn push(&mut self, source: Source) -> SourceId {
+        self.push_shared(Arc::new(source))
+    }
+
+    fn push_shared(&mut self, source: Arc<Source>) -> SourceId {
+        let id = SourceId(self.next_id);
+        self.next_id += 1;
+
+        if let (Some(origins), Some(origin)) = (self.origins.get_mut(), &source.origin) {
+            origins.entry(Arc::from(origin.as_str())).or_insert(id);
+        }
+
+        if let Some(counts) = self.counts.get_mut() {
+            counts.add(&source);
+        }
+
+        let digest = source.content_digest();
+
+        self.positions.insert(id, self.sources.len());
+        self.ids.push(id);
         self.sources.push(source);
+        self.remember_content(digest);
+
+        id
+    }
+
+    fn push_all(&mut self, sources: Vec<Source>) -> Vec<SourceId> {
+        self.sources.reserve(sources.len());
+        self.ids.reserve(sources.len());
+        self.positions.reserve(sources.len());
+
+        sources
+            .into_iter()
+            .map(|source| self.push(source))
+            .collect()
+    }
+
+    fn check_duplicates<'a, I: Iterator<Item = &'a Source>>(
+        &self,
+        sources: I,
+    ) -> Result<(), Error> {
+        let mut seen = HashSet::new();
+
+        for origin in sources.filter_map(|source| source.origin.as_deref()) {
+            if !seen.insert(origin) || self.named(origin).is_ok() {
+                return Err(Error::DuplicateSource(origin.to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Iterate over the identifiers of the sources in the source map, in order.
+    pub fn ids(&self) -> impl Iterator<Item = SourceId> + '_ {
+        self.ids.iter().copied()
+    }
+
+    /// Get the sources of the source map, in order.
+    ///
+    /// Sources are shared with the source maps derived from this one ( e.g. by `partition` ),
+    /// and cloned when they are mutably accessed while shared.
+    pub fn sources(&self) -> &[Arc<Source>] {
+        &self.sources
+    }
+
+    /// Iterate over the sources of the source map, in order.
+    pub fn iter(&self) -> Iter<'_> {
+        self.sources.iter().map(Arc::as_ref)
+    }
+
+    /// Iterate mutably over the sources of the source map, in order.
+    ///
+    /// Sources shared with other source maps are cloned as they are iterated.
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        // origins and contents may be changed through the returned references.
+        self.reset_indexes();
+
+        self.sources.iter_mut().map(make_mut)
+    }
+
+    /// Get the number of sources in the source map.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Check if the source map contains no sources.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Get the number of sources in the source map, per kind.
+    ///
+    /// Counts are kept up to date as sources are added and removed, they are only counted
+    /// again after the sources were mutably accessed.
+    pub fn counts(&self) -> SourceCounts {
+        *self.counts.get_or_init(|| SourceCounts::new(self.iter()))
+    }
+
+    /// Check the invariants of the internal indexes of the source map, panicking if one
+    /// does not hold.
+    ///
+    /// This is meant for tests and debugging, checks are only run in debug builds.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]);
+    /// let foo = map.add(Source::new(SourceKind::Script, "foo.ara", "")).unwrap();
+    /// map.add(Source::new(SourceKind::Definition, "bar.d.ara", "")).unwrap();
+    /// map.remove(foo);
+    ///
+    /// map.validate();
+    /// ```
+    pub fn validate(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        assert_eq!(
+            self.sources.len(),
+            self.ids.len(),
+            "sources and identifiers differ"
+        );
+        for (position, id) in self.ids.iter().enumerate() {
+            assert_eq!(
+                self.positions.get(id).copied(),
+                Some(position),
+                "wrong position for {id}"
+            );
+        }
+        assert_eq!(
+            self.positions.len(),
+            self.sources.len(),
+            "positions of removed sources are kept"
+        );
+
+        if let Some(origins) = self.origins.get() {
+            let mut expected = HashMap::new();
+            for (source, id) in self.sources.iter().zip(&self.ids) {
+                if let Some(origin) = &source.origin {
+                    expected.entry(origin.as_str()).or_insert(*id);
+                }
+            }
+
+            assert_eq!(origins.len(), expected.len(), "stale index of origins");
+            for (origin, id) in origins {
+                assert_eq!(
+                    expected.get(origin.as_ref()),
+                    Some(id),
+                    "stale origin {origin}"
+                );
+            }
+        }
+
+        if let Some(counts) = self.counts.get() {
+            assert_eq!(*counts, SourceCounts::new(self.iter()), "stale counts");
+        }
+
+        if let Some(filter) = self.content_filter.as_ref().and_then(OnceLock::get) {
+            for source in self.iter() {
+                assert!(
+                    filter.maybe_contains(source.content_digest()),
+                    "content of {} is missing from the filter",
+                    source.name()
+                );
+            }
+        }
+
+        for id in self.errors.keys() {
+            assert!(
+                self.position(*id).is_some(),
+                "errors attached to removed {id}"
+            );
+        }
+        for tombstone in self.tombstones() {
+            assert!(
+                self.position(tombstone.id).is_none(),
+                "tombstone of live {}",
+                tombstone.id
+            );
+        }
+    }
+
+    /// Get a source by its identifier.
+    ///
+    /// `None` is only returned for identifiers of removed sources, or of another source map.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]);
+    /// let id = map.add(Source::new(SourceKind::Script, "main.ara", "")).unwrap();
+    ///
+    /// assert_eq!(map.source(id).unwrap().name(), "main.ara");
+    /// ```
+    pub fn source(&self, id: SourceId) -> Option<&Source> {
+        self.position(id)
+            .map(|position| self.sources[position].as_ref())
+    }
+
+    /// Remove the source with the given identifier, returning it.
+    ///
+    /// The identifiers of the other sources stay valid, and the identifier of
+    /// the removed source is never reused.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]);
+    /// let foo = map.add(Source::new(SourceKind::Script, "foo.ara", "")).unwrap();
+    /// let bar = map.add(Source::new(SourceKind::Script, "bar.ara", "")).unwrap();
+    ///
+    /// assert_eq!(map.remove(foo).unwrap().name(), "foo.ara");
+    /// assert!(map.source(foo).is_none());
+    /// assert_eq!(map.source(bar).unwrap().name(), "bar.ara");
+    /// ```
+    pub fn remove(&mut self, id: SourceId) -> Option<Source> {
+        let position = self.position(id)?;
+
+        self.positions.remove(&id);
+        self.ids.remove(position);
+        let source = Arc::unwrap_or_clone(self.sources.remove(position));
+        if let Some(counts) = self.counts.get_mut() {
+            counts.remove(&source);
+        }
+        self.bury(id, &source);
+        self.errors.remove(&id);
+
+        for (position, id) in self.ids.iter().enumerate().skip(position) {
+            self.positions.insert(*id, position);
+        }
+
+        if let (Some(origins), Some(origin)) = (self.origins.get_mut(), &source.origin) {
+            if origins.get(origin.as_str()) == Some(&id) {
+                origins.remove(origin.as_str());
+
+                // another source with the same origin is now the first one.
+                let next = self
+                    .sources
+                    .iter()
+                    .position(|source| source.origin.as_ref() == Some(origin));
+                if let Some(next) = next {
+                    origins.insert(Arc::from(origin.as_str()), self.ids[next]);
+                }
+            }
+        }
+
+        Some(source)
+    }
+
+    /// Keep only the sources for which the given predicate returns `true`.
+    ///
+    /// The relative order of the kept sources is preserved, and their identifiers stay valid.
+    pub fn retain<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(&Source) -> bool,
+    {
+        let sources = std::mem::take(&mut self.sources);
+        let ids = std::mem::take(&mut self.ids);
+
+        for (source, id) in sources.into_iter().zip(ids) {
+            if predicate(&source) {
+                self.sources.push(source);
+                self.ids.push(id);
+            } else {
+                self.positions.remove(&id);
+                self.bury(id, &source);
+                self.errors.remove(&id);
+            }
+        }
+
+        self.reindex();
+    }
+
+    /// Rename the origins under the given prefix, e.g. after a directory was moved.
+    ///
+    /// The prefix matches whole path segments, so `src/foo` matches `src/foo` and
+    /// `src/foo/bar.ara`, but not `src/foobar.ara`. Identifiers, contents, and quarantines
+    /// are kept, and the identifiers of the renamed sources are returned in order.
+    ///
+    /// Renamed sources forget the file they were read from, so `refresh` reads them from
+    /// their new origin.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]);
+    /// let foo = map.add(Source::new(SourceKind::Script, "src/foo/foo.ara", "")).unwrap();
+    /// let bar = map.add(Source::new(SourceKind::Script, "src/foobar.ara", "")).unwrap();
+    ///
+    /// assert_eq!(map.rename_prefix("src/foo", "src/baz"), vec![foo]);
+    /// assert_eq!(map[foo].origin.as_deref(), Some("src/baz/foo.ara"));
+    /// assert_eq!(map[bar].origin.as_deref(), Some("src/foobar.ara"));
+    /// assert!(map.named("src/baz/foo.ara").is_ok());
+    /// ```
+    pub fn rename_prefix<O: AsRef<str>, N: AsRef<str>>(
+        &mut self,
+        old_prefix: O,
+        new_prefix: N,
+    ) -> Vec<SourceId> {
+        let old_prefix = old_prefix.as_ref().trim_end_matches('/');
+        let new_prefix = new_prefix.as_ref().trim_end_matches('/');
+
+        let rename = |origin: &str| -> Option<String> {
+            let rest = origin.strip_prefix(old_prefix)?;
+
+            (rest.is_empty() || rest.starts_with('/')).then(|| format!("{new_prefix}{rest}"))
+        };
+
+        let mut renamed = vec![];
+        for (source, id) in self.sources.iter_mut().zip(&self.ids) {
+            if let Some(origin) = source.origin.as_deref().and_then(rename) {
+                let source = Arc::make_mut(source);
+                source.origin = Some(origin);
+                source.stamp = FileStamp::default();
+                renamed.push(*id);
+            }
+        }
+
+        self.quarantined = std::mem::take(&mut self.quarantined)
+            .into_iter()
+            .map(|(origin, quarantine)| (rename(&origin).unwrap_or(origin), quarantine))
+            .collect();
+
+        if !renamed.is_empty() {
+            self.reindex();
+        }
+
+        renamed
+    }
+
+    /// Keep a bloom filter over the content digests of the sources, or stop keeping it.
+    ///
+    /// The filter makes `maybe_contains_content` answer without probing every source, e.g. to
+    /// detect renamed or duplicated files in the hot path of refresh cycles.
+    pub fn with_content_filter(mut self, content_filter: bool) -> SourceMap {
+        self.content_filter = if content_filter {
+            Some(OnceLock::new())
+        } else {
+            None
+        };
+
+        self
+    }
+
+    /// Check if a source may have the content of the given digest, see `Source::content_digest`.
+    ///
+    /// If `false` is returned, no source has this content. If the content filter is kept, `true`
+    /// may be returned for a content no source has, or had before its removal, otherwise
+    /// the sources are probed, and the answer is exact.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let map = SourceMap::new(vec![Source::new(SourceKind::Script, "foo.ara", "foo")])
+    ///     .with_content_filter(true);
+    ///
+    /// let moved = Source::new(SourceKind::Script, "bar.ara", "foo");
+    ///
+    /// assert!(map.maybe_contains_content(moved.content_digest()));
+    /// ```
+    pub fn maybe_contains_content(&self, digest: u64) -> bool {
+        match self.content_filter() {
+            Some(filter) => filter.maybe_contains(digest),
+            None => self
+                .sources
+                .iter()
+                .any(|source| source.content_digest() == digest),
+        }
+    }
+
+    fn content_filter(&self) -> Option<&BloomFilter> {
+        self.content_filter.as_ref().map(|filter| {
+            filter.get_or_init(|| {
+                let mut filter = BloomFilter::new((self.sources.len() * 2).max(64));
+                for source in &self.sources {
+                    filter.insert(source.content_digest());
+                }
+
+                filter
+            })
+        })
+    }
+
+    fn remember_content(&mut self, digest: u64) {
+        match self.content_filter.as_mut().and_then(OnceLock::get_mut) {
+            // the filter is resized when it is built again.
+            Some(filter) if filter.is_full() => self.content_filter = Some(OnceLock::new()),
+            Some(filter) => filter.insert(digest),
+            None => {}
+        }
+    }
+
+    /// Get the tombstones of the removed sources that were not acknowledged yet, in order of removal.
+    ///
+    /// If tombstones are not kept, no tombstones are returned, see `with_tombstones`.
+    pub fn tombstones(&self) -> &[Tombstone] {
+        self.tombstones.as_deref().unwrap_or_default()
+    }
+
+    /// Get the tombstone of the removed source with the given identifier, if it was not acknowledged yet.
+    pub fn tombstone(&self, id: SourceId) -> Option<&Tombstone> {
+        self.tombstones()
+            .iter()
+            .find(|tombstone| tombstone.id == id)
+    }
+
+    /// Acknowledge all tombstones, removing and returning them.
+    pub fn acknowledge_tombstones(&mut self) -> Vec<Tombstone> {
+        self.tombstones
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn bury(&mut self, id: SourceId, source: &Source) {
+        if let Some(tombstones) = &mut self.tombstones {
+            tombstones.push(Tombstone {
+                id,
+                origin: source.origin.clone(),
+                fingerprint: source.fingerprint(),
+            });
+        }
+    }
+
+    /// Attach an error to the source with the given identifier.
+    ///
+    /// This keeps loader or content errors ( e.g. a file that exists but could not be read )
+    /// alongside the source they concern, errors are dropped when their source is removed.
+    ///
+    /// If the source is not found, `Error::SourceNotFound` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::error::Error;
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::new(vec![]);
+    /// let id = map.add(Source::new(SourceKind::Script, "foo.ara", "")).unwrap();
+    ///
+    /// map.attach_error(id, Error::InvalidSource("file unreadable".to_string())).unwrap();
+    ///
+    /// assert_eq!(map.errors_for(id).len(), 1);
+    /// assert_eq!(map.errors_for(id)[0].to_string(), "invalid source: file unreadable");
+    /// ```
+    pub fn attach_error(&mut self, id: SourceId, error: Error) -> Result<(), Error> {
+        if self.position(id).is_none() {
+            return Err(Error::SourceNotFound(id.to_string()));
+        }
+
+        self.errors.entry(id).or_default().push(error);
+
+        Ok(())
+    }
+
+    /// Get the errors attached to the source with the given identifier, in order of attachment.
+    pub fn errors_for(&self, id: SourceId) -> &[Error] {
+        self.errors.get(&id).map(Vec::as_slice).unwrap_or_default()
     }
 
-    /// Get a source by its index.
+    /// Remove and return the errors attached to the source with the given identifier.
+    pub fn clear_errors(&mut self, id: SourceId) -> Vec<Error> {
+        self.errors.remove(&id).unwrap_or_default()
+    }
+
+    /// Get a source by its 1-based index.
     ///
     /// If the source is not found, `Error::SourceNotFound` is returned.
+    #[deprecated(note = "use `SourceMap::source` with a `SourceId` instead")]
     pub fn get(&self, index: usize) -> Result<&Source, Error> {
-        self.sources
-            .get(index - 1)
+        index
+            .checked_sub(1)
+            .and_then(|index| self.sources.get(index))
+            .map(Arc::as_ref)
             .ok_or_else(|| Error::SourceNotFound(index.to_string()))
     }
 
     /// Find a source by its origin.
     ///
-    /// If the source is not found, `Error::SourceNotFound` is returned.
-    pub fn named<T: Into<String>>(&self, name: T) -> Result<&Source, Error> {
-        let name = name.into();
+    /// If the source is not found, `Error::SourceNotFound` is returned. If several sources
+    /// share the same origin, the first one is returned.
+    ///
+    /// The lookup is done in constant time through an index of origins, it borrows the
+    /// given name, and does not allocate unless the source is not found. Use `id_of` to probe
+    /// origins that are likely missing.
+    ///
+    /// After sources were mutably accessed, the index is rebuilt once by the next lookup.
+    pub fn named<T: AsRef<str>>(&self, name: T) -> Result<&Source, Error> {
+        let name = name.as_ref();
 
-        self.sources
-            .iter()
-            .find(|source| source.origin == Some(name.clone()))
-            .ok_or(Error::SourceNotFound(name))
+        self.origins()
+            .get(name)
+            .and_then(|id| self.source(*id))
+            .ok_or_else(|| Error::SourceNotFound(name.to_string()))
+    }
+
+    /// Get the entry of the given origin, to get or lazily add its source in place.
+    ///
+    /// See `SourceEntry` for an example.
+    pub fn entry<T: Into<String>>(&mut self, origin: T) -> SourceEntry<'_> {
+        SourceEntry::new(self, origin.into())
+    }
+
+    /// Get the identifier of the first source with the given origin.
+    ///
+    /// The lookup is indexed like `named`, and does not allocate even if the origin is not
+    /// found, so resolvers can probe many candidate origins without caching the misses.
+    pub fn id_of<T: AsRef<str>>(&self, origin: T) -> Option<SourceId> {
+        self.origins().get(origin.as_ref()).copied()
+    }
+
+    fn origins(&self) -> &HashMap<Arc<str>, SourceId> {
+        self.origins.get_or_init(|| {
+            let mut origins = HashMap::new();
+            for (source, id) in self.sources.iter().zip(&self.ids) {
+                if let Some(origin) = &source.origin {
+                    origins.entry(Arc::from(origin.as_str())).or_insert(*id);
+                }
+            }
+
+            origins
+        })
+    }
+
+    /// Find all sources whose origin matches the given glob pattern.
+    ///
+    /// See `Glob` for the supported syntax, sources without an origin never match.
+    pub fn matching_glob<T: Into<String>>(&self, pattern: T) -> Vec<&Source> {
+        let glob = Glob::new(pattern);
+
+        self.iter()
+            .filter(|source| {
+                source
+                    .origin
+                    .as_ref()
+                    .is_some_and(|origin| glob.matches(origin))
+            })
+            .collect()
     }
 
     /// Merge two source maps.
@@ -45,60 +882,1481 @@ impl SourceMap {
     /// The sources of the other source map are appended to the current source map.
     ///
     /// The other source map is emptied.
-    pub fn merge(&mut self, other: &mut SourceMap) {
-        self.sources.append(&mut other.sources);
+    ///
+    /// The package boundaries and quarantines of the other source map are moved to the current source map.
+    ///
+    /// The identifiers of the merged sources in the current source map are returned.
+    ///
+    /// If a source of the other source map has the same origin as an existing source,
+    /// `Error::DuplicateSource` is returned, and both source maps are left unchanged.
+    ///
+    /// See `merge_with` to resolve such collisions instead.
+    pub fn merge(&mut self, other: &mut SourceMap) -> Result<Vec<SourceId>, Error> {
+        self.merge_with(other, MergeStrategy::Error)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Merge two source maps, resolving origin collisions with the given strategy.
+    ///
+    /// This behaves like `merge`, except for sources of the other source map with the same
+    /// origin as an existing source, or as an earlier source of the other source map:
+    ///
+    /// - `MergeStrategy::PreferExisting` drops the incoming source.
+    /// - `MergeStrategy::PreferIncoming` replaces the existing source in place, keeping its identifier.
+    /// - `MergeStrategy::Error` returns `Error::DuplicateSource`, leaving both source maps unchanged.
+    ///
+    /// The identifiers of the added and replaced sources are returned, dropped sources have none.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::MergeStrategy;
+    /// use ara_source::SourceMap;
+    ///
+    /// let mut map = SourceMap::single(Source::new(SourceKind::Definition, "foo.d.ara", "vendor"));
+    /// let mut local = SourceMap::single(Source::new(SourceKind::Definition, "foo.d.ara", "local"));
+    ///
+    /// map.merge_with(&mut local, MergeStrategy::PreferIncoming).unwrap();
+    ///
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.named("foo.d.ara").unwrap().content, "local");
+    /// ```
+    pub fn merge_with(
+        &mut self,
+        other: &mut SourceMap,
+        strategy: MergeStrategy,
+    ) -> Result<Vec<SourceId>, Error> {
+        if strategy == MergeStrategy::Error {
+            self.check_duplicates(other.iter())?;
+        }
 
-    use crate::source::SourceKind;
+        let incoming = std::mem::take(&mut other.ids);
+        let mut errors = std::mem::take(&mut other.errors);
+        other.positions.clear();
+        other.reset_indexes();
 
-    #[test]
-    fn test_source_map() {
-        let mut map = SourceMap::new(vec![]);
+        self.packages.append(&mut other.packages);
+        self.quarantined.extend(other.quarantined.drain());
 
-        map.add(Source::new(
-            SourceKind::Script,
-            "foo.ara",
-            "function foo(): void {}",
-        ));
-        map.add(Source::new(
-            SourceKind::Script,
-            "bar.ara",
-            "function bar(): void {}",
-        ));
+        let sources = std::mem::take(&mut other.sources);
+        let mut ids = Vec::with_capacity(sources.len());
 
-        assert_eq!(map.get(1).unwrap().origin, Some("foo.ara".to_string()));
-        assert_eq!(map.get(2).unwrap().origin, Some("bar.ara".to_string()));
-        assert!(map.get(3).is_err());
+        self.sources.reserve(sources.len());
+        self.ids.reserve(sources.len());
+        self.positions.reserve(sources.len());
 
-        assert_eq!(
-            map.named("foo.ara").unwrap().origin,
-            Some("foo.ara".to_string())
-        );
-        assert_eq!(
-            map.named("bar.ara").unwrap().origin,
-            Some("bar.ara".to_string())
-        );
-        assert!(map.named("baz.ara").is_err());
+        for (source, previous) in sources.into_iter().zip(incoming) {
+            let existing = source
+                .origin
+                .as_deref()
+                .and_then(|origin| self.id_of(origin));
 
-        let mut other = SourceMap::new(vec![]);
+            let id = match (existing, strategy) {
+                (Some(_), MergeStrategy::PreferExisting) => continue,
+                (Some(id), _) => {
+                    let position = self.position(id).expect("indexed source has a position.");
+                    let digest = source.content_digest();
+                    self.sources[position] = source;
+                    // remember the content once it is in place, as a full filter is rebuilt from the sources.
+                    self.remember_content(digest);
+                    self.errors.remove(&id);
 
-        other.add(Source::new(
-            SourceKind::Script,
-            "baz.ara",
-            "function baz(): void {}",
-        ));
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+
+                    id
+                }
+                (None, _) => {
+                    let id = self.push_shared(source);
+                    ids.push(id);
+
+                    id
+                }
+            };
+
+            if let Some(errors) = errors.remove(&previous) {
+                self.errors.entry(id).or_default().extend(errors);
+            }
+        }
+
+        Ok(ids)
+    }
+
+    /// Move all `SourceKind::Definition` sources before `SourceKind::Script` sources.
+    ///
+    /// The relative order of sources of the same kind is preserved, this lets
+    /// single-pass consumers register foreign symbols before compiling the code using them.
+    pub fn sort_definitions_first(&mut self) {
+        let mut entries = std::mem::take(&mut self.sources)
+            .into_iter()
+            .zip(std::mem::take(&mut self.ids))
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|(source, _)| source.kind != SourceKind::Definition);
+        (self.sources, self.ids) = entries.into_iter().unzip();
+
+        self.reindex();
+    }
+
+    /// Split the sources into `n` groups of roughly equal size in bytes.
+    ///
+    /// Sources keep their relative order within each group, and some groups may
+    /// be empty if there are fewer sources than groups. If `n` is 0, no groups are returned.
+    ///
+    /// The groups share their sources with this source map, nothing is copied until a
+    /// source is mutably accessed.
+    pub fn partition(&self, n: usize) -> Vec<SourceMap> {
+        if n == 0 {
+            return vec![];
+        }
+
+        let mut indices = (0..self.sources.len()).collect::<Vec<usize>>();
+        indices.sort_by_key(|index| std::cmp::Reverse(self.sources[*index].content.len()));
+
+        let mut groups = vec![(0, vec![]); n];
+        for index in indices {
+            let (size, members) = groups.iter_mut().min_by_key(|(size, _)| *size).unwrap();
+
+            *size += self.sources[index].content.len();
+            members.push(index);
+        }
+
+        groups
+            .into_iter()
+            .map(|(_, mut members)| {
+                members.sort_unstable();
+
+                SourceMap::shared(
+                    members
+                        .into_iter()
+                        .map(|index| Arc::clone(&self.sources[index])),
+                )
+            })
+            .collect()
+    }
+
+    /// Create a new source map containing only the `SourceKind::Definition` sources of this one.
+    ///
+    /// Package boundaries are kept, so the subset can be shipped as an API-only bundle.
+    ///
+    /// The subset shares its sources with this source map, like `partition`.
+    pub fn definitions_subset(&self) -> SourceMap {
+        let mut map = SourceMap::shared(
+            self.sources
+                .iter()
+                .filter(|source| source.kind == SourceKind::Definition)
+                .cloned(),
+        );
+
+        map.packages = self.packages.clone();
+
+        map
+    }
+
+    /// Create a source map from the given shared sources, without checking for duplicates.
+    fn shared<I: IntoIterator<Item = Arc<Source>>>(sources: I) -> SourceMap {
+        let mut map = SourceMap::new(vec![]);
+        for source in sources {
+            map.push_shared(source);
+        }
+
+        map
+    }
+
+    fn position(&self, id: SourceId) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// Rebuild the positions of all identifiers, and reset the index of origins.
+    fn reindex(&mut self) {
+        self.reset_indexes();
+
+        for (position, id) in self.ids.iter().enumerate() {
+            self.positions.insert(*id, position);
+        }
+    }
+
+    /// Reset the origins, the counts, and the content filter, to be built again when needed.
+    fn reset_indexes(&mut self) {
+        self.origins = OnceLock::new();
+        self.counts = OnceLock::new();
+        if self.content_filter.is_some() {
+            self.content_filter = Some(OnceLock::new());
+        }
+    }
+
+    /// Find the definition paired with the script of the given identifier.
+    ///
+    /// `dir/foo.ara` is paired with `dir/foo.d.ara`, or, if there is no such definition,
+    /// with a definition in a parallel tree that only differs by its top-level
+    /// directory ( e.g. `src/foo/bar.ara` and `stubs/foo/bar.d.ara` ).
+    ///
+    /// If the identifier is not of a script, `None` is returned.
+    pub fn paired_definition(&self, script: SourceId) -> Option<SourceId> {
+        self.paired(script, SourceKind::Script, SourceKind::Definition)
+    }
+
+    /// Find the script paired with the definition of the given identifier.
+    ///
+    /// See `paired_definition` for the pairing rules.
+    pub fn paired_script(&self, definition: SourceId) -> Option<SourceId> {
+        self.paired(definition, SourceKind::Definition, SourceKind::Script)
+    }
+
+    fn paired(&self, id: SourceId, from: SourceKind, to: SourceKind) -> Option<SourceId> {
+        let source = self.source(id)?;
+        if source.kind != from {
+            return None;
+        }
+
+        let (directory, stem) = pairing_key(source)?;
+
+        // the source in the same directory is found through the index of origins.
+        let extension = match to {
+            SourceKind::Definition => "d.ara",
+            SourceKind::Script => "ara",
+        };
+        let sibling = directory.join(format!("{stem}.{extension}"));
+        if let Some(sibling) = sibling.to_str().and_then(|origin| self.id_of(origin)) {
+            if self[sibling].kind == to {
+                return Some(sibling);
+            }
+        }
+
+        let candidates = self
+            .sources
+            .iter()
+            .zip(&self.ids)
+            .filter(|(candidate, _)| candidate.kind == to)
+            .filter_map(|(candidate, id)| Some((*id, pairing_key(candidate)?)));
+
+        let mut parallel = None;
+        for (candidate, (candidate_directory, candidate_stem)) in candidates {
+            if candidate_stem != stem {
+                continue;
+            }
+
+            // origins with other separators are not found through the index.
+            if candidate_directory == directory {
+                return Some(candidate);
+            }
+
+            if parallel.is_none() {
+                let mut a = directory.components();
+                let mut b = candidate_directory.components();
+                if a.next().is_some() && b.next().is_some() && a.as_path() == b.as_path() {
+                    parallel = Some(candidate);
+                }
+            }
+        }
+
+        parallel
+    }
+
+    /// Register a package boundary: sources under the given directory belong to the given package.
+    ///
+    /// When boundaries are nested, the innermost one wins.
+    pub fn register_package<D: Into<String>, N: Into<String>>(&mut self, directory: D, name: N) {
+        self.packages.push((directory.into(), name.into()));
+    }
+
+    /// Get the name of the package the source with the given identifier belongs to.
+    ///
+    /// If the source is not under any registered package boundary, or was removed, `None`
+    /// is returned.
+    pub fn package_of(&self, id: SourceId) -> Option<&str> {
+        let origin = Path::new(self.source(id)?.origin.as_ref()?);
+
+        self.packages
+            .iter()
+            .filter(|(directory, _)| origin.starts_with(directory))
+            .max_by_key(|(directory, _)| Path::new(directory).components().count())
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// Quarantine the source with the given identifier for the given duration.
+    ///
+    /// Quarantined sources are meant to be skipped by refresh loops, so a source
+    /// that repeatedly fails to load or parse does not keep being retried.
+    ///
+    /// The quarantine is kept by origin, so it outlives the removal of the source, and
+    /// applies to a source loaded again from the same origin, see `quarantined` and `release`.
+    /// Quarantining an already quarantined source replaces its quarantine.
+    ///
+    /// If the source was removed, `Error::SourceNotFound` is returned, and if it has no
+    /// origin, `Error::InvalidSource` is returned.
+    pub fn quarantine<R: Into<String>>(
+        &mut self,
+        id: SourceId,
+        reason: R,
+        duration: Duration,
+    ) -> Result<(), Error> {
+        let source = self
+            .source(id)
+            .ok_or_else(|| Error::SourceNotFound(id.to_string()))?;
+        let origin = source.origin.clone().ok_or_else(|| {
+            Error::InvalidSource(format!("inline source {id} cannot be quarantined"))
+        })?;
+
+        self.quarantined
+            .insert(origin, Quarantine::new(reason, duration));
+
+        Ok(())
+    }
+
+    /// Get the quarantine of the source with the given origin.
+    ///
+    /// If the source is not quarantined, or its quarantine has expired, `None` is returned.
+    pub fn quarantined<T: AsRef<str>>(&self, origin: T) -> Option<&Quarantine> {
+        self.quarantined
+            .get(origin.as_ref())
+            .filter(|quarantine| !quarantine.is_expired())
+    }
+
+    /// Release the source with the given origin from quarantine.
+    ///
+    /// Expired quarantines are released as well.
+    pub fn release<T: AsRef<str>>(&mut self, origin: T) -> Option<Quarantine> {
+        self.quarantined
+            .retain(|_, quarantine| !quarantine.is_expired());
+
+        self.quarantined.remove(origin.as_ref())
+    }
+
+    /// Reload the sources loaded from the given root, returning the identifiers of the changed sources.
+    ///
+    /// The content of each source with an origin is read again, and replaced if it changed,
+    /// rescanning pragmas if they were scanned before. Inline and quarantined sources are skipped.
+    ///
+    /// Sources loaded from a file within the root are read from that file, otherwise from
+    /// their origin within the root. Files whose size and modification time did not change
+    /// since they were last read are not read again.
+    ///
+    /// Load hooks and validators are not run again. If a source cannot be read, the error is
+    /// returned and no source is changed.
+    ///
+    /// If vanished sources are removed ( see `with_remove_vanished` ), sources whose file was
+    /// removed are removed as well, and their identifiers are returned along with the changed
+    /// ones. Otherwise, `Error::SourceVanished` is returned.
+    pub fn refresh<T: AsRef<Path>>(&mut self, root: &T) -> Result<Vec<SourceId>, Error> {
+        let root = absolute_root(root.as_ref());
+
+        let mut changes = vec![];
+        let mut removed = vec![];
+        for (index, source) in self.sources.iter().enumerate() {
+            let origin = match &source.origin {
+                Some(origin) if self.quarantined(origin).is_none() => origin,
+                _ => continue,
+            };
+
+            // lossy origins do not name their file, the file they were read from does.
+            let file = match source.stamp.file() {
+                Some(file) if relative_to(&root, &file).is_some() => file.to_path_buf(),
+                _ => root.join(origin),
+            };
+
+            let metadata = match std::fs::metadata(verbatim_path(&file)) {
+                Ok(metadata) => metadata,
+                Err(error) => match read_error(&file, error) {
+                    Error::SourceVanished(_) if self.remove_vanished => {
+                        removed.push(self.ids[index]);
+
+                        continue;
+                    }
+                    error => return Err(error),
+                },
+            };
+
+            if source.stamp.matches(&metadata) {
+                continue;
+            }
+
+            let content = std::fs::read_to_string(verbatim_path(&file))
+                .map_err(|error| read_error(&file, error))?;
+
+            changes.push((index, content, FileStamp::new(&file, &metadata)));
+        }
+
+        let mut changed = vec![];
+        for (index, content, stamp) in changes {
+            let source = Arc::make_mut(&mut self.sources[index]);
+
+            source.stamp = stamp;
+            if content == source.content {
+                continue;
+            }
+
+            source.content = content;
+            source.reset_line_index();
+            if source.pragmas.is_some() {
+                source.scan_pragmas();
+            }
+
+            let digest = source.content_digest();
+            self.remember_content(digest);
+            changed.push(self.ids[index]);
+        }
+
+        for id in removed {
+            self.remove(id);
+            changed.push(id);
+        }
+
+        Ok(changed)
+    }
+
+    /// Group the sources of the source map by the parent directory of their origin.
+    ///
+    /// Sources at the root have an empty directory, and sources without an origin are skipped.
+    ///
+    /// Directories are sorted, and sources within a directory keep their order in the map.
+    pub fn group_by_directory(&self) -> BTreeMap<String, Vec<&Source>> {
+        let mut groups: BTreeMap<String, Vec<&Source>> = BTreeMap::new();
+
+        for source in &self.sources {
+            let origin = match &source.origin {
+                Some(origin) => origin,
+                None => continue,
+            };
+
+            let directory = Path::new(origin)
+                .parent()
+                .map(|directory| directory.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            groups.entry(directory).or_default().push(source);
+        }
+
+        groups
+    }
+
+    /// Summarize the source map: counts by kind, total size, and the `top` largest sources.
+    ///
+    /// This is useful to diagnose why indexing a workspace is slow.
+    pub fn report(&self, top: usize) -> SourceMapReport<'_> {
+        SourceMapReport::new(self.iter(), top)
+    }
+
+    /// Get the combined fingerprints of the scripts and definitions of the source map.
+    ///
+    /// The fingerprints do not depend on the order of the sources, so a build system
+    /// can compare them between two loads to skip work when only scripts changed,
+    /// but definitions did not.
+    pub fn fingerprint_by_kind(&self) -> KindFingerprints {
+        KindFingerprints {
+            scripts: self.fingerprint_of(SourceKind::Script),
+            definitions: self.fingerprint_of(SourceKind::Definition),
+        }
+    }
+
+    fn fingerprint_of(&self, kind: SourceKind) -> u64 {
+        let mut fingerprints = self
+            .sources
+            .iter()
+            .filter(|source| source.kind == kind)
+            .map(|source| source.fingerprint())
+            .collect::<Vec<u64>>();
+
+        fingerprints.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        for fingerprint in fingerprints {
+            hasher.write_u64(fingerprint);
+        }
+
+        hasher.finish()
+    }
+}
+
+impl std::ops::Index<SourceId> for SourceMap {
+    type Output = Source;
+
+    /// Get the source with the given identifier.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was removed, or if the identifier is of another source map.
+    fn index(&self, id: SourceId) -> &Source {
+        match self.source(id) {
+            Some(source) => source,
+            None => panic!("source {id} is not in the source map."),
+        }
+    }
+}
+
+impl std::ops::IndexMut<SourceId> for SourceMap {
+    /// Get the source with the given identifier, mutably.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the source was removed, or if the identifier is of another source map.
+    fn index_mut(&mut self, id: SourceId) -> &mut Source {
+        let position = match self.position(id) {
+            Some(position) => position,
+            None => panic!("source {id} is not in the source map."),
+        };
+
+        // the origin and content may be changed through the returned reference.
+        self.reset_indexes();
+
+        make_mut(&mut self.sources[position])
+    }
+}
+
+impl IntoIterator for SourceMap {
+    type Item = Source;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.sources.into_iter().map(Arc::unwrap_or_clone)
+    }
+}
+
+impl<'a> IntoIterator for &'a SourceMap {
+    type Item = &'a Source;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut SourceMap {
+    type Item = &'a mut Source;
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl FromIterator<Source> for SourceMap {
+    fn from_iter<I: IntoIterator<Item = Source>>(iter: I) -> SourceMap {
+        SourceMap::new(iter.into_iter().collect())
+    }
+}
+
+/// Sources are added without checking for duplicate origins, see `SourceMap::add`.
+impl Extend<Source> for SourceMap {
+    fn extend<I: IntoIterator<Item = Source>>(&mut self, iter: I) {
+        for source in iter {
+            self.push(source);
+        }
+    }
+}
+
+/// Get the given source mutably, cloning it if it is shared, and discarding its line index
+/// as its content may be changed.
+fn make_mut(source: &mut Arc<Source>) -> &mut Source {
+    let source = Arc::make_mut(source);
+    source.reset_line_index();
+
+    source
+}
+
+fn pairing_key(source: &Source) -> Option<(&Path, &str)> {
+    let origin = Path::new(source.origin.as_ref()?);
+    let name = origin.file_name()?.to_str()?;
+    let stem = match source.kind {
+        SourceKind::Definition => name.strip_suffix(".d.ara")?,
+        SourceKind::Script => name.strip_suffix(".ara")?,
+    };
+
+    Some((origin.parent().unwrap_or(Path::new("")), stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::new(vec![]);
+
+        let foo = map
+            .add(Source::new(
+                SourceKind::Script,
+                "foo.ara",
+                "function foo(): void {}",
+            ))
+            .unwrap();
+        let bar = map
+            .add(Source::new(
+                SourceKind::Script,
+                "bar.ara",
+                "function bar(): void {}",
+            ))
+            .unwrap();
+
+        assert_eq!(map.source(foo).unwrap().origin, Some("foo.ara".to_string()));
+        assert_eq!(map.source(bar).unwrap().origin, Some("bar.ara".to_string()));
+        assert_eq!(map.ids().collect::<Vec<_>>(), vec![foo, bar]);
+
+        assert_eq!(
+            map.named("foo.ara").unwrap().origin,
+            Some("foo.ara".to_string())
+        );
+        assert_eq!(
+            map.named("bar.ara").unwrap().origin,
+            Some("bar.ara".to_string())
+        );
+        assert!(map.named("baz.ara").is_err());
+
+        let mut other = SourceMap::new(vec![]);
+
+        other
+            .add(Source::new(
+                SourceKind::Script,
+                "baz.ara",
+                "function baz(): void {}",
+            ))
+            .unwrap();
+
+        let merged = map.merge(&mut other).unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(map.source(foo).unwrap().origin, Some("foo.ara".to_string()));
+        assert_eq!(map.source(bar).unwrap().origin, Some("bar.ara".to_string()));
+        assert_eq!(
+            map.source(merged[0]).unwrap().origin,
+            Some("baz.ara".to_string())
+        );
+
+        assert!(other.source(foo).is_none());
+    }
+
+    #[test]
+    fn test_named_index() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "first"),
+            Source::new(SourceKind::Script, "foo.ara", "second"),
+        ]);
+        assert_eq!(map.named("foo.ara").unwrap().content, "first");
+
+        let mut other = SourceMap::single(Source::new(SourceKind::Definition, "bar.d.ara", ""));
+        map.merge(&mut other).unwrap();
+        assert!(other.named("bar.d.ara").is_err());
+
+        map.sort_definitions_first();
+        assert_eq!(map.named("bar.d.ara").unwrap().kind, SourceKind::Definition);
+        assert_eq!(map.named("foo.ara").unwrap().content, "first");
+
+        let subset = map.definitions_subset();
+        assert!(subset.named("bar.d.ara").is_ok());
+        assert!(subset.named("foo.ara").is_err());
+    }
+
+    #[test]
+    fn test_remove_and_retain() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "first"),
+            Source::new(SourceKind::Definition, "bar.d.ara", ""),
+            Source::new(SourceKind::Script, "foo.ara", "second"),
+            Source::new(SourceKind::Script, "baz.ara", ""),
+        ]);
+
+        let ids = map.ids().collect::<Vec<_>>();
+        let (foo, bar, duplicate, baz) = (ids[0], ids[1], ids[2], ids[3]);
+
+        map.sort_definitions_first();
+        assert_eq!(
+            map.ids().collect::<Vec<_>>(),
+            vec![bar, foo, duplicate, baz]
+        );
+        assert_eq!(map.source(foo).unwrap().content, "first");
+
+        assert_eq!(map.remove(foo).unwrap().content, "first");
+        assert!(map.remove(foo).is_none());
+        assert_eq!(map.named("foo.ara").unwrap().content, "second");
+        assert_eq!(map.source(baz).unwrap().name(), "baz.ara");
+        map.validate();
+
+        map.retain(|source| source.kind == SourceKind::Script);
+        assert_eq!(map.ids().collect::<Vec<_>>(), vec![duplicate, baz]);
+        assert!(map.source(bar).is_none());
+        assert!(map.named("bar.d.ara").is_err());
+        assert_eq!(map.source(baz).unwrap().name(), "baz.ara");
+
+        let qux = map
+            .add(Source::new(SourceKind::Script, "qux.ara", ""))
+            .unwrap();
+        assert!(![foo, bar, duplicate, baz].contains(&qux));
+        map.validate();
+    }
+
+    #[test]
+    fn test_positions_do_not_grow() {
+        let mut map = SourceMap::new(vec![Source::new(SourceKind::Script, "main.ara", "")]);
+
+        let mut last = map.ids().next().unwrap();
+        for _ in 0..100 {
+            let id = map
+                .add(Source::new(SourceKind::Script, "watched.ara", ""))
+                .unwrap();
+            assert!(id > last);
+
+            map.remove(id);
+            last = id;
+        }
+
+        assert_eq!(map.positions.len(), 1);
+        map.validate();
+    }
+
+    #[test]
+    fn test_tombstones() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "foo"),
+            Source::new(SourceKind::Script, "bar.ara", "bar"),
+            Source::inline(SourceKind::Script, "baz"),
+        ]);
+        let ids = map.ids().collect::<Vec<_>>();
+
+        map.remove(ids[0]);
+        assert!(map.tombstones().is_empty());
+
+        let mut map = map.with_tombstones(true);
+        let fingerprint = map[ids[1]].fingerprint();
+
+        map.retain(|source| source.origin.is_some());
+        map.remove(ids[1]);
+        assert_eq!(
+            map.tombstones(),
+            &[
+                Tombstone {
+                    id: ids[2],
+                    origin: None,
+                    fingerprint: Source::inline(SourceKind::Script, "baz").fingerprint(),
+                },
+                Tombstone {
+                    id: ids[1],
+                    origin: Some("bar.ara".to_string()),
+                    fingerprint,
+                },
+            ]
+        );
+        assert!(map.tombstone(ids[0]).is_none());
+        assert_eq!(map.tombstone(ids[1]).unwrap().fingerprint, fingerprint);
+
+        assert_eq!(map.acknowledge_tombstones().len(), 2);
+        assert!(map.tombstones().is_empty());
+
+        let map = map.with_tombstones(false);
+        assert!(map.tombstones().is_empty());
+    }
+
+    #[test]
+    fn test_attached_errors() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", ""),
+            Source::new(SourceKind::Script, "bar.ara", ""),
+        ]);
+        let ids = map.ids().collect::<Vec<_>>();
+
+        map.attach_error(ids[0], Error::InvalidSource("foo".to_string()))
+            .unwrap();
+        map.attach_error(ids[0], Error::InvalidSource("bar".to_string()))
+            .unwrap();
+        assert_eq!(map.errors_for(ids[0]).len(), 2);
+        assert!(map.errors_for(ids[1]).is_empty());
+
+        let mut other = SourceMap::new(vec![Source::new(SourceKind::Script, "baz.ara", "")]);
+        let baz = other.ids().next().unwrap();
+        other
+            .attach_error(baz, Error::InvalidSource("baz".to_string()))
+            .unwrap();
+
+        let merged = map.merge(&mut other).unwrap();
+        assert_eq!(
+            map.errors_for(merged[0])[0].to_string(),
+            "invalid source: baz"
+        );
+
+        map.remove(ids[0]);
+        assert!(map.errors_for(ids[0]).is_empty());
+        assert!(matches!(
+            map.attach_error(ids[0], Error::InvalidSource("foo".to_string())),
+            Err(Error::SourceNotFound(_))
+        ));
+
+        assert_eq!(map.clear_errors(merged[0]).len(), 1);
+        assert!(map.errors_for(merged[0]).is_empty());
+    }
+
+    #[test]
+    fn test_rename_prefix() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/foo", ""),
+            Source::new(SourceKind::Script, "src/foo/bar.ara", "bar"),
+            Source::new(SourceKind::Script, "src/foobar.ara", ""),
+            Source::inline(SourceKind::Script, ""),
+        ]);
+        let ids = map.ids().collect::<Vec<_>>();
+        map.quarantine(ids[1], "broken", Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(map.rename_prefix("src/foo/", "lib"), vec![ids[0], ids[1]]);
+        assert_eq!(map[ids[0]].origin.as_deref(), Some("lib"));
+        assert_eq!(map[ids[1]].origin.as_deref(), Some("lib/bar.ara"));
+        assert_eq!(map[ids[1]].content, "bar");
+        assert_eq!(map[ids[2]].origin.as_deref(), Some("src/foobar.ara"));
+        assert!(map.named("src/foo/bar.ara").is_err());
+        assert_eq!(map.named("lib/bar.ara").unwrap().content, "bar");
+        assert!(map.quarantined("lib/bar.ara").is_some());
+        assert!(map.quarantined("src/foo/bar.ara").is_none());
+        map.validate();
+
+        assert!(map.rename_prefix("missing", "lib").is_empty());
+    }
+
+    #[test]
+    fn test_rename_prefix_refresh() {
+        let root = TempDir::new("rename_prefix_refresh");
+        std::fs::create_dir_all(root.join("src/foo")).unwrap();
+        std::fs::write(root.join("src/foo/bar.ara"), "bar").unwrap();
+
+        let mut map = crate::loader::DirectorySourceLoader::new(&root)
+            .load(Path::new("src"))
+            .unwrap()
+            .with_remove_vanished(true);
+
+        std::fs::rename(root.join("src/foo"), root.join("src/baz")).unwrap();
+        let bar = map.rename_prefix("src/foo", "src/baz");
+        assert!(map.refresh(&root).unwrap().is_empty());
+        assert_eq!(map[bar[0]].content, "bar");
+
+        std::fs::write(root.join("src/baz/bar.ara"), "baz").unwrap();
+        assert_eq!(map.refresh(&root).unwrap(), bar);
+        assert_eq!(map.named("src/baz/bar.ara").unwrap().content, "baz");
+    }
+
+    #[test]
+    fn test_content_filter() {
+        let digest = |content: &str| Source::inline(SourceKind::Script, content).content_digest();
+
+        let map = SourceMap::new(vec![Source::new(SourceKind::Script, "foo.ara", "foo")]);
+        assert!(map.maybe_contains_content(digest("foo")));
+        assert!(!map.maybe_contains_content(digest("bar")));
+
+        let mut map = map.with_content_filter(true);
+        assert!(map.maybe_contains_content(digest("foo")));
+
+        let contents = (0..200).map(|index| index.to_string()).collect::<Vec<_>>();
+        map.add_all(
+            contents
+                .iter()
+                .map(|content| Source::inline(SourceKind::Script, content.as_str()))
+                .collect(),
+        )
+        .unwrap();
+        assert!(contents
+            .iter()
+            .all(|content| map.maybe_contains_content(digest(content))));
+
+        let id = map.ids().next().unwrap();
+        map[id].content = "bar".to_string();
+        assert!(map.maybe_contains_content(digest("bar")));
+
+        let map = map.with_content_filter(false);
+        assert!(!map.maybe_contains_content(digest("foo")));
+
+        // replacing a source while the filter is full rebuilds it with the incoming content.
+        let mut map = SourceMap::new(vec![]).with_content_filter(true);
+        let origins = (0..64)
+            .map(|index| format!("{index}.ara"))
+            .collect::<Vec<_>>();
+        for origin in &origins {
+            map.add(Source::new(
+                SourceKind::Script,
+                origin.as_str(),
+                origin.as_str(),
+            ))
+            .unwrap();
+        }
+
+        let mut other = SourceMap::new(vec![Source::new(SourceKind::Script, "0.ara", "qux")]);
+        map.merge_with(&mut other, MergeStrategy::PreferIncoming)
+            .unwrap();
+        assert!(map.maybe_contains_content(digest("qux")));
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut map = ["foo.ara", "bar.ara"]
+            .into_iter()
+            .map(|origin| Source::new(SourceKind::Script, origin, ""))
+            .collect::<SourceMap>();
+
+        map.extend(vec![Source::new(SourceKind::Definition, "baz.d.ara", "")]);
+        assert_eq!(map.len(), 3);
+
+        let names = (&map)
+            .into_iter()
+            .map(|source| source.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["foo.ara", "bar.ara", "baz.d.ara"]);
+
+        for source in &mut map {
+            if let Some(origin) = &mut source.origin {
+                origin.insert_str(0, "src/");
+            }
+        }
+
+        assert!(map.named("foo.ara").is_err());
+        assert_eq!(map.named("src/foo.ara").unwrap().name(), "src/foo.ara");
+
+        map.add(Source::inline(SourceKind::Script, "")).unwrap();
+        assert_eq!(map.named("src/bar.ara").unwrap().name(), "src/bar.ara");
+        assert_eq!(
+            map.iter().filter(|source| source.origin.is_none()).count(),
+            1
+        );
+
+        let sources = map.into_iter().collect::<Vec<Source>>();
+        assert_eq!(sources.len(), 4);
+    }
+
+    #[test]
+    fn test_index() {
+        let mut map = SourceMap::new(vec![]);
+        let foo = map
+            .add(Source::new(SourceKind::Script, "foo.ara", ""))
+            .unwrap();
+
+        assert_eq!(map[foo].name(), "foo.ara");
+
+        map[foo].origin = Some("bar.ara".to_string());
+        assert_eq!(map.named("bar.ara").unwrap().name(), "bar.ara");
+        assert!(map.named("foo.ara").is_err());
+
+        // a content of the same length does not keep the line index of the previous one.
+        map[foo].content = "\n".to_string();
+        assert_eq!(map[foo].position_at(1).unwrap().line, 2);
+        map[foo].content = "a".to_string();
+        assert_eq!(map[foo].position_at(1).unwrap().line, 1);
+        map.iter_mut()
+            .for_each(|source| source.content = "\n".to_string());
+        assert_eq!(map[foo].position_at(1).unwrap().line, 2);
+
+        let mut map = map.with_content_filter(true);
+        map[foo].content = "function bar(): void {}".to_string();
+        assert!(map.maybe_contains_content(map[foo].content_digest()));
+    }
+
+    #[test]
+    #[should_panic(expected = "source #0 is not in the source map.")]
+    fn test_index_removed() {
+        let mut map = SourceMap::new(vec![]);
+        let foo = map
+            .add(Source::new(SourceKind::Script, "foo.ara", ""))
+            .unwrap();
+        map.remove(foo);
+
+        let _ = &map[foo];
+    }
+
+    #[test]
+    fn test_duplicate_source() {
+        let mut map = SourceMap::single(Source::new(SourceKind::Script, "foo.ara", ""));
+
+        assert!(matches!(
+            map.add(Source::new(SourceKind::Script, "foo.ara", "")),
+            Err(Error::DuplicateSource(origin)) if origin == "foo.ara"
+        ));
+        assert!(map.add(Source::inline(SourceKind::Script, "")).is_ok());
+        assert!(map.add(Source::inline(SourceKind::Script, "")).is_ok());
+
+        assert!(map
+            .add_all(vec![
+                Source::new(SourceKind::Script, "bar.ara", ""),
+                Source::new(SourceKind::Script, "bar.ara", ""),
+            ])
+            .is_err());
+        assert!(map.named("bar.ara").is_err());
+
+        let mut other = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "baz.ara", ""),
+            Source::new(SourceKind::Script, "foo.ara", ""),
+        ]);
+        assert!(map.merge(&mut other).is_err());
+        assert_eq!(map.len(), 3);
+        assert_eq!(other.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_with() {
+        let vendor = || {
+            SourceMap::new(vec![
+                Source::new(SourceKind::Definition, "foo.d.ara", "vendor"),
+                Source::new(SourceKind::Definition, "bar.d.ara", "vendor"),
+            ])
+        };
+        let local = || {
+            SourceMap::new(vec![
+                Source::new(SourceKind::Definition, "bar.d.ara", "local"),
+                Source::new(SourceKind::Script, "main.ara", "local"),
+            ])
+        };
+
+        let mut map = vendor();
+        let mut other = local();
+        assert_eq!(other.counts().scripts, 1);
+        let bar = map.ids().nth(1).unwrap();
+        let ids = map
+            .merge_with(&mut other, MergeStrategy::PreferIncoming)
+            .unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(ids[0], bar);
+        assert_eq!(map.named("bar.d.ara").unwrap().content, "local");
+        assert_eq!(map.source(bar).unwrap().content, "local");
+        map.validate();
+        assert_eq!(other.counts(), SourceCounts::default());
+        other.validate();
+
+        let mut map = vendor();
+        let ids = map
+            .merge_with(&mut local(), MergeStrategy::PreferExisting)
+            .unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(ids.len(), 1);
+        assert_eq!(map.named("bar.d.ara").unwrap().content, "vendor");
+        assert_eq!(map.named("main.ara").unwrap().content, "local");
+
+        let mut map = vendor();
+        let mut other = local();
+        assert!(map.merge_with(&mut other, MergeStrategy::Error).is_err());
+        assert_eq!(map.len(), 2);
+        assert_eq!(other.len(), 2);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_get() {
+        let map = SourceMap::single(Source::new(SourceKind::Script, "foo.ara", ""));
+
+        assert_eq!(map.get(1).unwrap().name(), "foo.ara");
+        assert!(map.get(0).is_err());
+        assert!(map.get(2).is_err());
+    }
+
+    #[test]
+    fn test_add_all() {
+        let mut map = SourceMap::single(Source::new(SourceKind::Script, "a.ara", ""));
+
+        let ids = map
+            .add_all(vec![
+                Source::new(SourceKind::Script, "b.ara", ""),
+                Source::new(SourceKind::Script, "c.ara", ""),
+            ])
+            .unwrap();
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(ids.len(), 2);
+        assert_eq!(map.source(ids[1]).unwrap().name(), "c.ara");
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut map = SourceMap::new(vec![]);
+
+        assert!(map.is_empty());
+        assert_eq!(map.counts(), SourceCounts::default());
+
+        map.add(Source::new(SourceKind::Script, "foo.ara", ""))
+            .unwrap();
+        map.add(Source::new(SourceKind::Definition, "bar.d.ara", ""))
+            .unwrap();
+        let inline = map.add(Source::inline(SourceKind::Script, "")).unwrap();
+
+        assert!(!map.is_empty());
+        assert_eq!(map.len(), 3);
+        assert_eq!(
+            map.counts(),
+            SourceCounts {
+                scripts: 2,
+                definitions: 1,
+                inline: 1,
+            }
+        );
+        map.validate();
+
+        map.remove(inline);
+        assert_eq!(
+            map.counts(),
+            SourceCounts {
+                scripts: 1,
+                definitions: 1,
+                inline: 0,
+            }
+        );
+        map.validate();
+
+        // sources may change kind through mutable access.
+        for source in map.iter_mut() {
+            source.kind = SourceKind::Definition;
+        }
+        assert_eq!(
+            map.counts(),
+            SourceCounts {
+                scripts: 0,
+                definitions: 2,
+                inline: 0,
+            }
+        );
+        map.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "stale counts")]
+    #[cfg(debug_assertions)]
+    fn test_validate() {
+        let mut map = SourceMap::new(vec![Source::new(SourceKind::Script, "foo.ara", "")]);
+
+        map.counts.get_mut().unwrap().scripts = 2;
+        map.validate();
+    }
+
+    #[test]
+    fn test_single() {
+        let map = SourceMap::single(Source::new(
+            SourceKind::Script,
+            "main.ara",
+            "function main(): void {}",
+        ));
+
+        assert_eq!(map.sources().len(), 1);
+        assert_eq!(map.source(SourceId(0)).unwrap().name(), "main.ara");
+        assert!(map.origins.get().is_none());
+
+        // the index of origins is built once it is needed.
+        let mut map = map;
+        assert_eq!(map.id_of("main.ara"), Some(SourceId(0)));
+        assert!(map
+            .add(Source::new(SourceKind::Script, "main.ara", ""))
+            .is_err());
+        assert_eq!(
+            map.add(Source::new(SourceKind::Script, "lib.ara", ""))
+                .unwrap(),
+            SourceId(1)
+        );
+    }
+
+    #[test]
+    fn test_group_by_directory() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/main.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/foo.d.ara", ""),
+            Source::new(SourceKind::Script, "src/lib.ara", ""),
+            Source::new(SourceKind::Script, "build.ara", ""),
+            Source::inline(SourceKind::Script, ""),
+        ]);
+
+        let groups = map.group_by_directory();
+        let names = |directory: &str| {
+            groups[directory]
+                .iter()
+                .map(|source| source.name())
+                .collect::<Vec<&str>>()
+        };
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(names(""), vec!["build.ara"]);
+        assert_eq!(names("src"), vec!["src/main.ara", "src/lib.ara"]);
+        assert_eq!(names("vendor/foo"), vec!["vendor/foo/foo.d.ara"]);
+    }
+
+    #[test]
+    fn test_matching_glob() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/main.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/foo.d.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/bar/bar.d.ara", ""),
+            Source::inline(SourceKind::Definition, ""),
+        ]);
+
+        let names = |pattern: &str| {
+            map.matching_glob(pattern)
+                .iter()
+                .map(|source| source.name())
+                .collect::<Vec<&str>>()
+        };
+
+        assert_eq!(
+            names("vendor/**/*.d.ara"),
+            vec!["vendor/foo/foo.d.ara", "vendor/foo/bar/bar.d.ara"]
+        );
+        assert_eq!(names("src/*.ara"), vec!["src/main.ara"]);
+        assert!(names("*.ara").is_empty());
+    }
+
+    #[test]
+    fn test_paired_sources() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/foo.ara", ""),
+            Source::new(SourceKind::Definition, "src/foo.d.ara", ""),
+            Source::new(SourceKind::Script, "src/bar/baz.ara", ""),
+            Source::new(SourceKind::Definition, "stubs/bar/baz.d.ara", ""),
+            Source::new(SourceKind::Script, "src/qux.ara", ""),
+            Source::new(SourceKind::Definition, "stubs/foo.d.ara", ""),
+        ]);
+
+        let source = |index: usize| map.ids().nth(index - 1).unwrap();
+        let name = |id: Option<SourceId>| id.map(|id| map[id].name().to_string());
+
+        assert_eq!(
+            name(map.paired_definition(source(1))),
+            Some("src/foo.d.ara".to_string())
+        );
+        assert_eq!(
+            name(map.paired_script(source(2))),
+            Some("src/foo.ara".to_string())
+        );
+        assert_eq!(
+            name(map.paired_definition(source(3))),
+            Some("stubs/bar/baz.d.ara".to_string())
+        );
+        assert_eq!(
+            name(map.paired_script(source(4))),
+            Some("src/bar/baz.ara".to_string())
+        );
+        assert_eq!(name(map.paired_definition(source(5))), None);
+        assert_eq!(
+            name(map.paired_script(source(6))),
+            Some("src/foo.ara".to_string())
+        );
+        assert_eq!(name(map.paired_definition(source(2))), None);
+    }
+
+    #[test]
+    fn test_package_of() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/main.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/foo.d.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/bar/bar.d.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foobar/foobar.d.ara", ""),
+            Source::inline(SourceKind::Definition, ""),
+        ]);
+
+        map.register_package("vendor/foo", "foo");
+        map.register_package("vendor/foo/bar", "bar");
+
+        let package_of = |index: usize| map.package_of(map.ids().nth(index - 1).unwrap());
+
+        assert_eq!(package_of(1), None);
+        assert_eq!(package_of(2), Some("foo"));
+        assert_eq!(package_of(3), Some("bar"));
+        assert_eq!(package_of(4), None);
+        assert_eq!(package_of(5), None);
+    }
+
+    #[test]
+    fn test_sort_definitions_first() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "a.ara", ""),
+            Source::new(SourceKind::Definition, "b.d.ara", ""),
+            Source::new(SourceKind::Script, "c.ara", ""),
+            Source::new(SourceKind::Definition, "d.d.ara", ""),
+        ]);
+
+        map.sort_definitions_first();
+
+        assert_eq!(
+            map.iter()
+                .map(|source| source.name())
+                .collect::<Vec<&str>>(),
+            vec!["b.d.ara", "d.d.ara", "a.ara", "c.ara"]
+        );
+    }
+
+    #[test]
+    fn test_partition() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "a.ara", "a".repeat(5)),
+            Source::new(SourceKind::Script, "b.ara", "b".repeat(8)),
+            Source::new(SourceKind::Script, "c.ara", "c".repeat(3)),
+            Source::new(SourceKind::Script, "d.ara", "d".repeat(4)),
+        ]);
+
+        let groups = map
+            .partition(2)
+            .iter()
+            .map(|group| {
+                group
+                    .sources
+                    .iter()
+                    .map(|source| source.name().to_string())
+                    .collect::<Vec<String>>()
+            })
+            .collect::<Vec<Vec<String>>>();
+
+        assert_eq!(groups, vec![vec!["b.ara", "c.ara"], vec!["a.ara", "d.ara"]]);
+
+        // groups share their sources with the map, until they are changed.
+        let mut groups = map.partition(2);
+        assert!(Arc::ptr_eq(&groups[0].sources()[0], &map.sources()[1]));
+
+        let b = groups[0].ids().next().unwrap();
+        groups[0][b].content.clear();
+        assert!(!Arc::ptr_eq(&groups[0].sources()[0], &map.sources()[1]));
+        assert_eq!(map.sources()[1].content, "b".repeat(8));
+
+        assert_eq!(map.partition(8).len(), 8);
+        assert!(map.partition(0).is_empty());
+    }
+
+    #[test]
+    fn test_definitions_subset() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "src/main.ara", ""),
+            Source::new(SourceKind::Definition, "vendor/foo/foo.d.ara", ""),
+        ]);
+        map.register_package("vendor/foo", "foo");
+
+        let subset = map.definitions_subset();
+
+        assert_eq!(subset.len(), 1);
+        assert!(subset.named("src/main.ara").is_err());
+        assert_eq!(
+            subset.package_of(subset.id_of("vendor/foo/foo.d.ara").unwrap()),
+            Some("foo")
+        );
+        assert_eq!(map.len(), 2);
+        assert!(Arc::ptr_eq(&subset.sources()[0], &map.sources()[1]));
+    }
+
+    #[test]
+    fn test_quarantine() {
+        let mut map = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "main.ara", ""),
+            Source::new(SourceKind::Script, "expired.ara", ""),
+            Source::new(SourceKind::Script, "forever.ara", ""),
+            Source::inline(SourceKind::Script, ""),
+        ]);
+        let ids = map.ids().collect::<Vec<_>>();
+
+        assert!(map.quarantined("main.ara").is_none());
+
+        for (id, duration) in
+            ids.iter()
+                .zip([Duration::from_secs(60), Duration::ZERO, Duration::MAX])
+        {
+            map.quarantine(*id, "syntax error", duration).unwrap();
+        }
+        assert!(map
+            .quarantine(ids[3], "syntax error", Duration::MAX)
+            .is_err());
+
+        assert_eq!(map.quarantined("main.ara").unwrap().reason, "syntax error");
+        assert!(map.quarantined("expired.ara").is_none());
+        assert_eq!(map.quarantined("forever.ara").unwrap().until, None);
+
+        // quarantines outlive their source.
+        map.remove(ids[2]);
+        assert!(map
+            .quarantine(ids[2], "syntax error", Duration::MAX)
+            .is_err());
+        assert!(map.quarantined("forever.ara").is_some());
+
+        assert!(map.release("main.ara").is_some());
+        assert!(map.quarantined("main.ara").is_none());
+        assert!(map.release("expired.ara").is_none());
+    }
+
+    #[test]
+    fn test_refresh() {
+        let root = TempDir::new("refresh");
+        std::fs::write(root.join("foo.ara"), "// ara-language-version: 1\n").unwrap();
+        std::fs::write(root.join("bar.ara"), "").unwrap();
+        std::fs::write(root.join("baz.ara"), "").unwrap();
+
+        let mut map = crate::loader::DirectorySourceLoader::new(&root)
+            .with_pragmas(true)
+            .load_with_report(&root)
+            .unwrap()
+            .0;
+        map.add(Source::inline(SourceKind::Script, "inline"))
+            .unwrap();
+        assert!(map.refresh(&root).unwrap().is_empty());
+
+        // files whose size and modification time did not change are not read again.
+        let modified = std::fs::metadata(root.join("foo.ara"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        std::fs::write(root.join("foo.ara"), "// ara-language-version: 2\n").unwrap();
+        let foo = std::fs::File::options()
+            .write(true)
+            .open(root.join("foo.ara"))
+            .unwrap();
+        foo.set_modified(modified).unwrap();
+        assert!(map.refresh(&root).unwrap().is_empty());
+
+        foo.set_modified(modified + Duration::from_secs(1)).unwrap();
+        drop(foo);
+        std::fs::write(root.join("baz.ara"), "function baz(): void {}").unwrap();
+        let baz = map.id_of("baz.ara").unwrap();
+        map.quarantine(baz, "syntax error", Duration::from_secs(60))
+            .unwrap();
+
+        let changed = map.refresh(&root).unwrap();
+        assert_eq!(changed.len(), 1);
+
+        let foo = map.source(changed[0]).unwrap();
+        assert_eq!(foo.origin.as_deref(), Some("foo.ara"));
+        assert_eq!(
+            foo.pragmas.as_ref().unwrap().language_version.as_deref(),
+            Some("2")
+        );
+        assert_eq!(map.named("baz.ara").unwrap().content, "");
+
+        std::fs::remove_file(root.join("bar.ara")).unwrap();
+        assert!(matches!(map.refresh(&root), Err(Error::SourceVanished(_))));
+
+        let mut map = map.with_remove_vanished(true);
+        let bar = map.refresh(&root).unwrap();
+        assert_eq!(bar.len(), 1);
+        assert!(map.named("bar.ara").is_err());
+        assert!(map.tombstone(bar[0]).is_none());
+
+        std::fs::write(root.join("bar.ara"), "").unwrap();
+        FileSourceLoader::new(&root)
+            .load_into(Path::new("bar.ara"), &mut map)
+            .unwrap();
+        std::fs::remove_file(root.join("bar.ara")).unwrap();
+
+        let mut map = map.with_tombstones(true);
+        let bar = map.refresh(&root).unwrap();
+        assert_eq!(bar.len(), 1);
+        assert!(map.named("bar.ara").is_err());
+        assert_eq!(
+            map.tombstone(bar[0]).unwrap().origin.as_deref(),
+            Some("bar.ara")
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_by_kind() {
+        let script = Source::new(SourceKind::Script, "foo.ara", "function foo(): void {}");
+        let definition = Source::new(SourceKind::Definition, "bar.d.ara", "function bar(): void;");
+
+        let map = SourceMap::new(vec![script.clone(), definition.clone()]);
+        let reversed = SourceMap::new(vec![definition.clone(), script]);
+
+        assert_eq!(map.fingerprint_by_kind(), reversed.fingerprint_by_kind());
 
-        map.merge(&mut other);
+        let changed = SourceMap::new(vec![
+            Source::new(SourceKind::Script, "foo.ara", "function foo(): int {}"),
+            definition,
+        ]);
 
-        assert_eq!(map.get(1).unwrap().origin, Some("foo.ara".to_string()));
-        assert_eq!(map.get(2).unwrap().origin, Some("bar.ara".to_string()));
-        assert_eq!(map.get(3).unwrap().origin, Some("baz.ara".to_string()));
+        let before = map.fingerprint_by_kind();
+        let after = changed.fingerprint_by_kind();
 
-        assert!(other.get(1).is_err());
+        assert_ne!(before.scripts, after.scripts);
+        assert_eq!(before.definitions, after.definitions);
     }
 }
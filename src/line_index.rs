@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// The offsets of the start of each line of a content, see `Source::line_index`.
+///
+/// Lines are separated by `\n`, as in `Source::len_lines`, lines and columns are 1-based,
+/// and columns are counted in bytes.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::line_index::LineIndex;
+///
+/// let index = LineIndex::new("foo\r\nbar\n");
+///
+/// assert_eq!(index.line_count(), 3);
+/// assert_eq!(index.line_range(1), Some(0..3));
+/// assert_eq!(index.line_range(2), Some(5..8));
+/// assert_eq!(index.line_range(3), Some(9..9));
+/// assert_eq!(index.offset_to_line_col(6), Some((2, 2)));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+    // the end of each line, before its `\n` or `\r\n` line ending.
+    ends: Vec<usize>,
+    // whether each line is ascii, so its utf-16 columns are its byte columns.
+    ascii: Vec<bool>,
+}
+
+impl LineIndex {
+    /// Compute the line index of the given content.
+    pub fn new(content: &str) -> LineIndex {
+        let mut starts = vec![0];
+        let mut ends = vec![];
+
+        for (offset, _) in content.match_indices('\n') {
+            starts.push(offset + 1);
+            if content[..offset].ends_with('\r') {
+                ends.push(offset - 1);
+            } else {
+                ends.push(offset);
+            }
+        }
+
+        ends.push(content.len());
+
+        let ascii = starts
+            .iter()
+            .zip(&ends)
+            .map(|(start, end)| content[*start..*end].is_ascii())
+            .collect();
+
+        LineIndex {
+            starts,
+            ends,
+            ascii,
+        }
+    }
+
+    /// Get the number of lines.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Get the byte range of the given line, without its line ending.
+    ///
+    /// If the line does not exist, `None` is returned.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let index = line.checked_sub(1)?;
+
+        Some(*self.starts.get(index)?..self.ends[index])
+    }
+
+    /// Get the line and column of the given byte offset.
+    ///
+    /// The end of the content is a valid offset, if the offset is past it, `None` is returned.
+    pub fn offset_to_line_col(&self, offset: usize) -> Option<(usize, usize)> {
+        if offset > self.ends[self.ends.len() - 1] {
+            return None;
+        }
+
+        let index = self.starts.partition_point(|start| *start <= offset) - 1;
+
+        Some((index + 1, offset - self.starts[index] + 1))
+    }
+
+    /// Get the byte offset of the given line and column.
+    ///
+    /// If the line does not exist, or the column is past its end, `None` is returned.
+    pub fn line_col_to_offset(&self, line: usize, column: usize) -> Option<usize> {
+        let range = self.line_range(line)?;
+        let offset = range.start + column.checked_sub(1)?;
+
+        (offset <= range.end).then_some(offset)
+    }
+
+    /// Get the length of the indexed content, in bytes.
+    pub(crate) fn content_len(&self) -> usize {
+        self.ends[self.ends.len() - 1]
+    }
+
+    /// Check if the given line only contains ascii characters.
+    pub(crate) fn is_ascii(&self, line: usize) -> bool {
+        self.ascii[line - 1]
+    }
+}
+
+/// A lazily computed line index, ignored when comparing and hashing sources.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineIndexCache(OnceLock<LineIndex>);
+
+impl LineIndexCache {
+    /// Get the cached line index, or a fresh one if the length of the content changed since.
+    pub(crate) fn get_or_init(&self, content: &str) -> Cow<'_, LineIndex> {
+        let index = self.0.get_or_init(|| LineIndex::new(content));
+
+        if index.content_len() == content.len() {
+            Cow::Borrowed(index)
+        } else {
+            Cow::Owned(LineIndex::new(content))
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.0 = OnceLock::new();
+    }
+}
+
+impl PartialEq for LineIndexCache {
+    fn eq(&self, _: &LineIndexCache) -> bool {
+        true
+    }
+}
+
+impl Eq for LineIndexCache {}
+
+impl Hash for LineIndexCache {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::position::Span;
+    use crate::source::Source;
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_line_index() {
+        let index = LineIndex::new("");
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.line_range(1), Some(0..0));
+        assert_eq!(index.line_range(0), None);
+        assert_eq!(index.line_range(2), None);
+        assert_eq!(index.offset_to_line_col(0), Some((1, 1)));
+        assert_eq!(index.offset_to_line_col(1), None);
+
+        let index = LineIndex::new("a\nbc\r\n\nd");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_range(2), Some(2..4));
+        assert_eq!(index.line_range(3), Some(6..6));
+        assert_eq!(index.line_range(4), Some(7..8));
+        assert_eq!(index.offset_to_line_col(1), Some((1, 2)));
+        assert_eq!(index.offset_to_line_col(2), Some((2, 1)));
+        assert_eq!(index.offset_to_line_col(8), Some((4, 2)));
+        assert_eq!(index.line_col_to_offset(2, 3), Some(4));
+        assert_eq!(index.line_col_to_offset(2, 4), None);
+        assert_eq!(index.line_col_to_offset(2, 0), None);
+
+        let mut source = Source::inline(SourceKind::Script, "foo\nbar");
+        assert_eq!(source.line_index().line_count(), 2);
+        assert_eq!(source.line_index().line_count(), source.len_lines());
+
+        source.content.push('\n');
+        assert_eq!(source.line_index().line_count(), 3);
+        assert_eq!(source, Source::inline(SourceKind::Script, "foo\nbar\n"));
+
+        source.reset_line_index();
+        assert_eq!(source.line_index().line_count(), 3);
+        source.content = "foo\nba\n\n".to_string();
+        assert_eq!(source.line_index().line_count(), 3);
+        source.reset_line_index();
+        assert_eq!(source.line_index().line_count(), 4);
+
+        // a stale index of the same length never slices within a character.
+        source.content = "fo\né\n\n\n".to_string();
+        let span = Span::new(
+            source.position_at(4).unwrap(),
+            source.position_at(5).unwrap(),
+        );
+        assert!(source.snippet(span, 0).is_none());
+
+        source.reset_line_index();
+        assert_eq!(source.snippet(span, 0).unwrap().lines[0].content, "é");
+    }
+}
@@ -1,7 +1,26 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io::ErrorKind;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 use crate::error::Error;
+use crate::event::EventLog;
+use crate::event::LoaderEvent;
+use crate::glob::Glob;
+use crate::ignore::IgnoreRules;
+use crate::ignore::ARA_IGNORE_FILE;
+use crate::ignore::GIT_IGNORE_FILE;
+use crate::path::is_windows_reserved_name;
+use crate::path::normalize;
+use crate::path::relative_to;
+use crate::path::verbatim_path;
+use crate::report::LoadReport;
+use crate::report::SkipReason;
+use crate::source::FileStamp;
 use crate::source::Source;
 use crate::source::SourceKind;
 use crate::SourceMap;
@@ -9,7 +28,15 @@ use crate::SourceMap;
 pub const ARA_SCRIPT_EXTENSION: &str = "ara";
 pub const ARA_DEFINTION_EXTENSION: &str = "d.ara";
 
+pub const CONVENTION_SCRIPTS_DIRECTORY: &str = "src";
+pub const CONVENTION_STUBS_DIRECTORY: &str = "stubs";
+
+/// The entries marking the root of a project, see `find_project_root`.
+pub const PROJECT_ROOT_MARKERS: [&str; 2] = [ARA_IGNORE_FILE, ".git"];
+
 /// Load a source map from the given directories.
+///
+/// Definitions of all directories are placed before scripts.
 pub fn load_directories<T: AsRef<Path>, C: AsRef<Path>>(
     root: T,
     directories: Vec<C>,
@@ -19,108 +46,396 @@ pub fn load_directories<T: AsRef<Path>, C: AsRef<Path>>(
     let loader = DirectorySourceLoader::new(&root);
 
     for directory in directories {
-        loader.load_into(&directory, &mut map)?;
+        loader.load_into(directory.as_ref(), &mut map)?;
     }
 
+    map.sort_definitions_first();
+
     Ok(map)
 }
 
+/// Load a source map containing the given file.
+///
+/// The root of the source is the project root of the file ( see `find_project_root` ),
+/// or the directory of the file if it is not within a project, and its kind is inferred
+/// from its extension.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::loader::load_file;
+/// use ara_source::source::SourceKind;
+///
+/// let file = format!("{}/examples/fixture/vendor/bar/bar.d.ara", env!("CARGO_MANIFEST_DIR"));
+/// let map = load_file(&file).unwrap();
+///
+/// assert_eq!(map.len(), 1);
+/// assert_eq!(map.sources()[0].kind, SourceKind::Definition);
+/// ```
+pub fn load_file<T: AsRef<Path>>(file: T) -> Result<SourceMap, Error> {
+    let file = match file.as_ref() {
+        file if file.is_relative() => std::env::current_dir()?.join(file),
+        file => file.to_path_buf(),
+    };
+
+    let directory = normalize(&file)
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| {
+            Error::InvalidSource(format!(
+                "source `{}` is not supported.",
+                file.to_string_lossy()
+            ))
+        })?;
+
+    let root = find_project_root(&directory).unwrap_or(directory);
+
+    FileSourceLoader::new(&root).load(&file)
+}
+
+/// Find the project root of the given path, the closest directory containing it, or one of
+/// its ancestors, that contains one of the `PROJECT_ROOT_MARKERS`.
+///
+/// If no such directory exists, `None` is returned.
+pub fn find_project_root<T: AsRef<Path>>(path: &T) -> Option<PathBuf> {
+    normalize(path)
+        .ancestors()
+        .find(|directory| {
+            PROJECT_ROOT_MARKERS
+                .iter()
+                .any(|marker| verbatim_path(&directory.join(marker)).exists())
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Load a source map from the files matching the given glob patterns.
+///
+/// Files matched by more than one pattern are loaded once, definitions are placed before scripts.
+pub fn load_globs<T: AsRef<Path>, P: AsRef<str>>(
+    root: T,
+    patterns: Vec<P>,
+) -> Result<SourceMap, Error> {
+    GlobSourceLoader::new(&root).load_patterns(patterns)
+}
+
+/// A loader of sources.
+///
+/// The trait is object safe, so loaders can be stored and composed as `Box<dyn SourceLoader>`.
 pub trait SourceLoader: std::fmt::Debug {
     /// Check if the given name is supported by this loader.
     ///
     /// If `true` is returned, `load` *MUST NOT* return `Error::InvalidSource`.
-    fn supports<T: AsRef<Path>>(&self, name: &T) -> bool;
+    fn supports(&self, name: &Path) -> bool;
 
     /// Load a source map from the given name.
     ///
     /// The source name can contain a path, directory, or any other information.
     ///
     /// If the source name is not valid, `Error::InvalidSource` is returned.
-    fn load<T: AsRef<Path>>(&self, name: &T) -> Result<SourceMap, Error>;
+    fn load(&self, name: &Path) -> Result<SourceMap, Error>;
 
     /// Load a source from the given name and merge it into the given source map.
-    fn load_into<T: AsRef<Path>>(&self, name: &T, map: &mut SourceMap) -> Result<(), Error> {
+    fn load_into(&self, name: &Path, map: &mut SourceMap) -> Result<(), Error> {
         let mut source = self.load(name)?;
 
-        map.merge(&mut source);
+        map.merge(&mut source)?;
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
+/// The decision of a loader about a path, see `FileSourceLoader::explain`
+/// and `DirectorySourceLoader::explain`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InclusionDecision {
+    /// The path is a file that is loaded as a source of the given kind.
+    Included(SourceKind),
+
+    /// The path is a directory that is traversed.
+    Traversed,
+
+    /// The path is outside of the loader root.
+    OutsideRoot,
+
+    /// The path does not exist, or is not of the expected type.
+    NotFound,
+
+    /// The file name is reserved on the current platform ( e.g. `CON` on Windows ).
+    ReservedName,
+
+    /// The file extension is not a supported source extension.
+    UnsupportedExtension,
+
+    /// The path is not valid UTF-8, and lossy origins are not enabled.
+    NonUtf8Path,
+
+    /// The path, or one of its parent directories within the root, is hidden ( starts with `.` ).
+    Hidden,
+
+    /// The path, or one of its parent directories within the root, is matched by an ignore file.
+    Ignored,
+
+    /// The path is within a subtree excluded with `DirectorySourceLoader::exclude_subtree`.
+    Excluded,
+}
+
+/// A hook called with each source loaded by a loader, see `FileSourceLoader::on_source_loaded`.
+pub type SourceHook = Box<dyn Fn(&mut Source) + Send + Sync>;
+
+/// A validator called with each source and its content when first read, see `FileSourceLoader::with_validator`.
+pub type SourceValidator = Box<dyn Fn(&Source, &str) -> Result<(), String> + Send + Sync>;
+
 pub struct FileSourceLoader {
     pub root: PathBuf,
+
+    pragmas: bool,
+    lossy_origins: bool,
+    hooks: Vec<SourceHook>,
+    validators: Vec<SourceValidator>,
 }
 
 impl FileSourceLoader {
     pub fn new<T: AsRef<Path>>(root: &T) -> FileSourceLoader {
         FileSourceLoader {
-            root: root.as_ref().to_path_buf(),
+            root: absolute_root(root.as_ref()),
+            pragmas: false,
+            lossy_origins: false,
+            hooks: vec![],
+            validators: vec![],
         }
     }
-}
 
-impl SourceLoader for FileSourceLoader {
-    fn supports<T: AsRef<Path>>(&self, file: &T) -> bool {
-        let file = file.as_ref();
-        let file = if file.is_relative() {
-            self.root.join(file)
-        } else {
-            file.to_path_buf()
-        };
+    /// Scan the header of each loaded source for pragmas, see `Source::scan_pragmas`.
+    pub fn with_pragmas(mut self, pragmas: bool) -> FileSourceLoader {
+        self.pragmas = pragmas;
 
-        if !file.is_file() {
-            return false;
-        }
+        self
+    }
 
-        match file.extension() {
-            Some(extension) => {
-                let extension = match extension.to_str() {
-                    Some(extension) => extension,
-                    None => {
-                        return false;
-                    }
-                };
+    /// Load files whose path is not valid UTF-8, replacing invalid sequences in
+    /// their origin with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// By default, such files are not supported, and loading them returns `Error::NonUtf8Path`.
+    pub fn with_lossy_origins(mut self, lossy_origins: bool) -> FileSourceLoader {
+        self.lossy_origins = lossy_origins;
 
-                if extension == ARA_SCRIPT_EXTENSION {
-                    return true;
-                }
+        self
+    }
 
-                false
-            }
-            None => false,
-        }
+    /// Call the given hook with each loaded source, before it is returned.
+    ///
+    /// Hooks can tag, reclassify, or preprocess sources, they are called in the
+    /// order they were registered, after pragmas are scanned.
+    pub fn on_source_loaded<F>(mut self, hook: F) -> FileSourceLoader
+    where
+        F: Fn(&mut Source) + Send + Sync + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+
+        self
+    }
+
+    /// Validate the content of each source when it is first read.
+    ///
+    /// Validators are called in the order they were registered, before pragmas are scanned,
+    /// the first rejection is returned as `Error::InvalidContent`.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    ///
+    /// use ara_source::error::Error;
+    /// use ara_source::loader::FileSourceLoader;
+    /// use ara_source::loader::SourceLoader;
+    ///
+    /// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+    ///
+    /// let loader = FileSourceLoader::new(&root).with_validator(|_, content| {
+    ///     match content.lines().any(|line| line.len() > 10) {
+    ///         true => Err("line is longer than 10 bytes".to_string()),
+    ///         false => Ok(()),
+    ///     }
+    /// });
+    ///
+    /// assert!(matches!(
+    ///     loader.load(Path::new("src/main.ara")),
+    ///     Err(Error::InvalidContent(origin, _)) if origin == "src/main.ara"
+    /// ));
+    /// ```
+    pub fn with_validator<F>(mut self, validator: F) -> FileSourceLoader
+    where
+        F: Fn(&Source, &str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validators.push(Box::new(validator));
+
+        self
     }
 
-    fn load<T: AsRef<Path>>(&self, file: &T) -> Result<SourceMap, Error> {
-        let file = file.as_ref();
+    /// Load a source from the given file, forcing the given kind.
+    ///
+    /// This overrides the extension-based classification, e.g. for generated
+    /// stub files that use the `.ara` extension.
+    pub fn load_as<T: AsRef<Path>>(&self, file: &T, kind: SourceKind) -> Result<SourceMap, Error> {
+        self.read(file.as_ref(), Some(kind))
+    }
 
-        if !self.supports(&file) {
-            return Err(Error::InvalidSource(format!(
-                "source `{}` is not supported.",
-                file.to_string_lossy()
-            )));
+    /// Explain whether the given file is loaded by this loader, and why.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::loader::FileSourceLoader;
+    /// use ara_source::loader::InclusionDecision;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let root = format!("{}/examples/", env!("CARGO_MANIFEST_DIR"));
+    /// let loader = FileSourceLoader::new(&root);
+    ///
+    /// assert_eq!(
+    ///     loader.explain(&"fixture/src/main.ara"),
+    ///     InclusionDecision::Included(SourceKind::Script)
+    /// );
+    /// assert_eq!(loader.explain(&"simple.rs"), InclusionDecision::UnsupportedExtension);
+    /// assert_eq!(loader.explain(&"missing.ara"), InclusionDecision::NotFound);
+    /// assert_eq!(loader.explain(&"../Cargo.toml"), InclusionDecision::OutsideRoot);
+    /// ```
+    pub fn explain<T: AsRef<Path>>(&self, file: &T) -> InclusionDecision {
+        let file = resolve(&self.root, file.as_ref());
+
+        self.explain_resolved(&file, || verbatim_path(&file).is_file())
+    }
+
+    /// Explain the given resolved file, checking whether it is a file only when needed.
+    pub(crate) fn explain_resolved<F>(&self, file: &Path, is_file: F) -> InclusionDecision
+    where
+        F: FnOnce() -> bool,
+    {
+        if !is_within(&self.root, file) {
+            return InclusionDecision::OutsideRoot;
         }
 
-        let file = if file.is_relative() {
-            self.root.join(file)
-        } else {
-            file.to_path_buf()
-        };
+        if cfg!(windows) && is_windows_reserved_name(&file) {
+            return InclusionDecision::ReservedName;
+        }
 
-        let content = std::fs::read_to_string(&file)?;
-        let origin = file
-            .strip_prefix(&self.root)
-            .map(|path| path.to_string_lossy())
-            .unwrap();
-        let kind = if origin.ends_with(ARA_DEFINTION_EXTENSION) {
-            SourceKind::Definition
+        if !is_file() {
+            return InclusionDecision::NotFound;
+        }
+
+        if file.extension() != Some(OsStr::new(ARA_SCRIPT_EXTENSION)) {
+            return InclusionDecision::UnsupportedExtension;
+        }
+
+        if !self.lossy_origins && file.to_str().is_none() {
+            return InclusionDecision::NonUtf8Path;
+        }
+
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let is_definition = name
+            .strip_suffix(ARA_DEFINTION_EXTENSION)
+            .is_some_and(|stem| stem.ends_with('.'));
+
+        if is_definition {
+            InclusionDecision::Included(SourceKind::Definition)
         } else {
-            SourceKind::Script
+            InclusionDecision::Included(SourceKind::Script)
+        }
+    }
+
+    fn read(&self, file: &Path, kind: Option<SourceKind>) -> Result<SourceMap, Error> {
+        let (file, kind) = self.prepare(file, kind, self.explain(&file))?;
+        let metadata = std::fs::metadata(verbatim_path(&file)).ok();
+        let content = std::fs::read_to_string(verbatim_path(&file))
+            .map_err(|error| read_error(&file, error))?;
+
+        self.finish(&file, kind, content, metadata.as_ref())
+    }
+
+    /// Resolve the given file, and the kind it is loaded as, before its content is read.
+    pub(crate) fn prepare(
+        &self,
+        file: &Path,
+        kind: Option<SourceKind>,
+        decision: InclusionDecision,
+    ) -> Result<(PathBuf, SourceKind), Error> {
+        let inferred = match decision {
+            InclusionDecision::Included(kind) => kind,
+            InclusionDecision::NonUtf8Path => {
+                return Err(Error::NonUtf8Path(resolve(&self.root, file)));
+            }
+            _ => {
+                return Err(Error::InvalidSource(format!(
+                    "source `{}` is not supported.",
+                    file.to_string_lossy()
+                )));
+            }
         };
 
-        Ok(SourceMap::new(vec![Source::new(kind, origin, content)]))
+        Ok((resolve(&self.root, file), kind.unwrap_or(inferred)))
+    }
+
+    /// Build the source of the given file from its content, validating it and running hooks.
+    ///
+    /// The metadata of the file, read before its content, stamps the source for refreshes.
+    pub(crate) fn finish(
+        &self,
+        file: &Path,
+        kind: SourceKind,
+        content: String,
+        metadata: Option<&Metadata>,
+    ) -> Result<SourceMap, Error> {
+        let origin = relative_to(&self.root, &file)
+            .map(|path| path.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                Error::InvalidSource(format!(
+                    "source `{}` is not within the root.",
+                    file.to_string_lossy()
+                ))
+            })?;
+
+        let mut source = Source::new(kind, origin, content);
+        if let Some(metadata) = metadata {
+            source.stamp = FileStamp::new(file, metadata);
+        }
+
+        for validator in &self.validators {
+            validator(&source, &source.content).map_err(|message| {
+                Error::InvalidContent(source.origin.clone().unwrap_or_default(), message)
+            })?;
+        }
+
+        if self.pragmas {
+            source.scan_pragmas();
+        }
+
+        for hook in &self.hooks {
+            hook(&mut source);
+        }
+
+        Ok(SourceMap::single(source))
+    }
+}
+
+impl SourceLoader for FileSourceLoader {
+    fn supports(&self, file: &Path) -> bool {
+        matches!(self.explain(&file), InclusionDecision::Included(_))
+    }
+
+    fn load(&self, file: &Path) -> Result<SourceMap, Error> {
+        self.read(file, None)
+    }
+}
+
+impl std::fmt::Debug for FileSourceLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileSourceLoader")
+            .field("root", &self.root)
+            .field("pragmas", &self.pragmas)
+            .field("lossy_origins", &self.lossy_origins)
+            .field("hooks", &self.hooks.len())
+            .field("validators", &self.validators.len())
+            .finish()
     }
 }
 
@@ -128,97 +443,1331 @@ impl SourceLoader for FileSourceLoader {
 pub struct DirectorySourceLoader {
     pub root: PathBuf,
 
-    loader: FileSourceLoader,
+    pub(crate) loader: FileSourceLoader,
+    skip_unreadable: bool,
+    skip_hidden: bool,
+    ignore: IgnoreRules,
+    gitignore: bool,
+    gitignore_cache: Mutex<HashMap<PathBuf, IgnoreRules>>,
+    excluded: Vec<PathBuf>,
+    event_log: Option<EventLog>,
 }
 
 impl DirectorySourceLoader {
     pub fn new<T: AsRef<Path>>(root: &T) -> DirectorySourceLoader {
         DirectorySourceLoader {
-            root: root.as_ref().to_path_buf(),
+            root: absolute_root(root.as_ref()),
             loader: FileSourceLoader::new(root),
+            skip_unreadable: false,
+            skip_hidden: true,
+            ignore: IgnoreRules::default(),
+            gitignore: false,
+            gitignore_cache: Mutex::new(HashMap::new()),
+            excluded: vec![],
+            event_log: None,
         }
     }
-}
 
-impl SourceLoader for DirectorySourceLoader {
-    fn supports<T: AsRef<Path>>(&self, directory: &T) -> bool {
-        let directory = directory.as_ref();
-        let directory = if directory.is_relative() {
-            self.root.join(directory)
+    /// Skip files and directories that cannot be read instead of failing the whole load.
+    ///
+    /// This includes paths removed between the directory scan and the read, which
+    /// otherwise fail the load with `Error::SourceVanished`.
+    ///
+    /// Skipped paths are recorded in the report returned by `load_with_report`.
+    pub fn with_skip_unreadable(mut self, skip_unreadable: bool) -> DirectorySourceLoader {
+        self.skip_unreadable = skip_unreadable;
+
+        self
+    }
+
+    /// Skip hidden files and directories ( e.g. `.git`, `.idea` ), without walking them.
+    ///
+    /// Only the part of the path within the root is considered, this is enabled by default.
+    pub fn with_skip_hidden(mut self, skip_hidden: bool) -> DirectorySourceLoader {
+        self.skip_hidden = skip_hidden;
+
+        self
+    }
+
+    /// Skip the files and directories matched by the `.araignore` file at the root, see `IgnoreRules`.
+    ///
+    /// The file is read when this option is enabled, a missing or unreadable file ignores nothing.
+    pub fn with_araignore(mut self, araignore: bool) -> DirectorySourceLoader {
+        self.ignore = if araignore {
+            std::fs::read_to_string(verbatim_path(&self.root.join(ARA_IGNORE_FILE)))
+                .map(IgnoreRules::parse)
+                .unwrap_or_default()
         } else {
-            directory.to_path_buf()
+            IgnoreRules::default()
         };
 
-        if !directory.starts_with(&self.root) {
-            return false;
+        self
+    }
+
+    /// Skip the files and directories matched by `.gitignore` files, see `IgnoreRules`.
+    ///
+    /// Only `.gitignore` files within the root are consulted, each applying to its own
+    /// directory, and rules of deeper files take precedence. This is disabled by default.
+    pub fn with_gitignore(mut self, gitignore: bool) -> DirectorySourceLoader {
+        self.gitignore = gitignore;
+
+        self
+    }
+
+    /// Skip the given subtree, relative to the root, along with everything within it.
+    ///
+    /// Unlike ignore rules, this matches a single root-relative path, not a pattern, e.g.
+    /// `vendor` skips the `vendor` directory at the root, but not `src/vendor`.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    ///
+    /// use ara_source::loader::DirectorySourceLoader;
+    /// use ara_source::loader::SourceLoader;
+    ///
+    /// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+    /// let loader = DirectorySourceLoader::new(&root).exclude_subtree(&"vendor");
+    ///
+    /// assert_eq!(loader.load(Path::new(".")).unwrap().len(), 1);
+    /// ```
+    pub fn exclude_subtree<T: AsRef<Path>>(mut self, path: &T) -> DirectorySourceLoader {
+        self.excluded
+            .push(normalize(&resolve(&self.root, path.as_ref())));
+
+        self
+    }
+
+    /// Scan the header of each loaded source for pragmas, see `Source::scan_pragmas`.
+    pub fn with_pragmas(mut self, pragmas: bool) -> DirectorySourceLoader {
+        self.loader = self.loader.with_pragmas(pragmas);
+
+        self
+    }
+
+    /// Load files whose path is not valid UTF-8, see `FileSourceLoader::with_lossy_origins`.
+    pub fn with_lossy_origins(mut self, lossy_origins: bool) -> DirectorySourceLoader {
+        self.loader = self.loader.with_lossy_origins(lossy_origins);
+
+        self
+    }
+
+    /// Call the given hook with each loaded source, see `FileSourceLoader::on_source_loaded`.
+    pub fn on_source_loaded<F>(mut self, hook: F) -> DirectorySourceLoader
+    where
+        F: Fn(&mut Source) + Send + Sync + 'static,
+    {
+        self.loader = self.loader.on_source_loaded(hook);
+
+        self
+    }
+
+    /// Validate the content of each source when it is first read, see `FileSourceLoader::with_validator`.
+    pub fn with_validator<F>(mut self, validator: F) -> DirectorySourceLoader
+    where
+        F: Fn(&Source, &str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.loader = self.loader.with_validator(validator);
+
+        self
+    }
+
+    /// Record the decisions taken by this loader in an in-memory log of the given capacity.
+    ///
+    /// Once the capacity is reached, the oldest events are dropped.
+    pub fn with_event_log(mut self, capacity: usize) -> DirectorySourceLoader {
+        self.event_log = Some(EventLog::new(capacity));
+
+        self
+    }
+
+    /// Get the events recorded by this loader, from the oldest to the newest.
+    ///
+    /// If the event log is not enabled, no events are returned.
+    pub fn events(&self) -> Vec<LoaderEvent> {
+        self.event_log
+            .as_ref()
+            .map(|event_log| event_log.events())
+            .unwrap_or_default()
+    }
+
+    /// Explain whether the given path is traversed or loaded by this loader, and why.
+    ///
+    /// Directories within the root are traversed, files are explained by
+    /// `FileSourceLoader::explain`.
+    pub fn explain<T: AsRef<Path>>(&self, path: &T) -> InclusionDecision {
+        let path = resolve(&self.root, path.as_ref());
+
+        self.explain_resolved(&path, || PathType::of(&path))
+    }
+
+    /// Explain the given resolved path, getting its type only when needed.
+    ///
+    /// The `.gitignore` files of the directories of the path are read unless they are cached,
+    /// see `uncached_ignore_directories`.
+    pub(crate) fn explain_resolved<F>(&self, path: &Path, path_type: F) -> InclusionDecision
+    where
+        F: FnOnce() -> PathType,
+    {
+        if !is_within(&self.root, path) {
+            return InclusionDecision::OutsideRoot;
         }
 
-        if !directory.is_dir() {
-            return false;
+        if self.skip_hidden && is_hidden(&self.root, path) {
+            return InclusionDecision::Hidden;
+        }
+
+        if self
+            .excluded
+            .iter()
+            .any(|subtree| normalize(&path).starts_with(subtree))
+        {
+            return InclusionDecision::Excluded;
+        }
+
+        let path_type = path_type();
+
+        if self.is_ignored(path, path_type.is_directory) {
+            return InclusionDecision::Ignored;
+        }
+
+        if path_type.is_directory {
+            return InclusionDecision::Traversed;
+        }
+
+        self.loader.explain_resolved(path, || path_type.is_file)
+    }
+
+    /// Forget the cached `.gitignore` files, to pick up changes made since they were read.
+    pub(crate) fn clear_ignore_cache(&self) {
+        self.gitignore_cache.lock().unwrap().clear();
+    }
+
+    /// Get the directories, from the root to the parent of the given path, whose `.gitignore`
+    /// files are needed to explain it, and are not cached yet.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn uncached_ignore_directories(&self, path: &Path) -> Vec<PathBuf> {
+        if !self.gitignore {
+            return vec![];
         }
 
-        true
+        let parts = match relative_parts(&self.root, path) {
+            Some(parts) => parts,
+            None => return vec![],
+        };
+
+        let cache = self.gitignore_cache.lock().unwrap();
+
+        // the same keys as `is_ignored`, so the cached rules are found there.
+        (0..parts.len())
+            .map(|depth| self.root.join(parts[..depth].join("/")))
+            .filter(|directory| !cache.contains_key(directory))
+            .collect()
+    }
+
+    /// Cache the content of the `.gitignore` file of the given directory, if it has one.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn cache_ignore_file(&self, directory: PathBuf, content: Option<String>) {
+        self.gitignore_cache.lock().unwrap().insert(
+            directory,
+            content.map(IgnoreRules::parse).unwrap_or_default(),
+        );
     }
 
-    fn load<T: AsRef<Path>>(&self, directory: &T) -> Result<SourceMap, Error> {
+    /// Load a source map from the given directory, along with a report of the loaded
+    /// and skipped paths.
+    ///
+    /// Excluded paths ( see `explain` ) and broken symbolic links are always skipped,
+    /// unreadable paths are only skipped if `with_skip_unreadable` is enabled.
+    ///
+    /// Definitions are placed before scripts in the returned source map.
+    pub fn load_with_report<T: AsRef<Path>>(
+        &self,
+        directory: &T,
+    ) -> Result<(SourceMap, LoadReport), Error> {
         let directory = directory.as_ref();
-        if !self.supports(&directory) {
+
+        // pick up changes to .gitignore files made since the previous load.
+        self.clear_ignore_cache();
+
+        let decision = self.explain(&directory);
+        if decision != InclusionDecision::Traversed {
+            self.record(LoaderEvent::Excluded(
+                resolve(&self.root, directory),
+                decision,
+            ));
+
             return Err(Error::InvalidSource(format!(
                 "source `{}` is not supported.",
                 directory.to_string_lossy()
             )));
         }
 
-        let directory = if directory.is_relative() {
-            self.root.join(directory)
-        } else {
-            directory.to_path_buf()
-        };
+        let directory = resolve(&self.root, directory);
 
         let mut map = SourceMap::new(vec![]);
+        let mut report = LoadReport::default();
+
+        self.load_directory(&directory, &mut map, &mut report)?;
 
-        let entries = std::fs::read_dir(directory)?;
+        map.sort_definitions_first();
+
+        Ok((map, report))
+    }
+
+    fn load_directory(
+        &self,
+        directory: &Path,
+        map: &mut SourceMap,
+        report: &mut LoadReport,
+    ) -> Result<(), Error> {
+        let entries = match std::fs::read_dir(verbatim_path(&directory)) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                if self.skip_unreadable {
+                    self.skip(report, directory, SkipReason::Vanished);
+
+                    return Ok(());
+                }
+
+                return Err(Error::SourceVanished(directory.to_path_buf()));
+            }
+            Err(error) if self.skip_unreadable => {
+                self.skip(report, directory, SkipReason::Unreadable(error.kind()));
+
+                return Ok(());
+            }
+            Err(error) => return Err(error.into()),
+        };
 
         for entry in entries {
-            let entry = entry.unwrap();
-            let path = entry.path();
+            let path = directory.join(entry?.file_name());
+
+            match self.explain(&path) {
+                InclusionDecision::Traversed => self.load_directory(&path, map, report)?,
+                InclusionDecision::Included(_) => match self.loader.load_into(&path, map) {
+                    Ok(()) => {
+                        report.load(&path);
+
+                        self.record(LoaderEvent::Loaded(path));
+                    }
+                    Err(Error::IoError(error)) if self.skip_unreadable => {
+                        self.skip(report, &path, SkipReason::Unreadable(error.kind()));
+                    }
+                    Err(Error::SourceVanished(_)) if self.skip_unreadable => {
+                        self.skip(report, &path, SkipReason::Vanished);
+                    }
+                    Err(error) => return Err(error),
+                },
+                InclusionDecision::NotFound if is_broken_symlink(&path) => {
+                    self.skip(report, &path, SkipReason::BrokenSymlink);
+                }
+                decision => {
+                    report.skip(&path, SkipReason::Excluded(decision));
 
-            if path.is_dir() {
-                self.load_into(&path, &mut map)?;
-            } else if self.loader.supports(&path) {
-                self.loader.load_into(&path, &mut map)?;
+                    self.record(LoaderEvent::Excluded(path, decision));
+                }
             }
         }
 
-        Ok(map)
+        Ok(())
+    }
+
+    fn skip(&self, report: &mut LoadReport, path: &Path, reason: SkipReason) {
+        report.skip(&path, reason);
+
+        self.record(LoaderEvent::Skipped(path.to_path_buf(), reason));
+    }
+
+    fn is_ignored(&self, path: &Path, is_directory: bool) -> bool {
+        if self.ignore.is_empty() && !self.gitignore {
+            return false;
+        }
+
+        let parts = match relative_parts(&self.root, path) {
+            Some(parts) => parts,
+            None => return false,
+        };
+
+        if self.ignore.is_ignored(parts.join("/"), is_directory) {
+            return true;
+        }
+
+        if !self.gitignore {
+            return false;
+        }
+
+        let mut cache = self.gitignore_cache.lock().unwrap();
+
+        // a path is ignored if it, or one of its parent directories, is ignored by the
+        // deepest .gitignore file with a matching rule.
+        (1..=parts.len()).any(|length| {
+            let is_directory = length < parts.len() || is_directory;
+
+            (0..length).rev().find_map(|depth| {
+                let directory = self.root.join(parts[..depth].join("/"));
+                let rules = cache.entry(directory).or_insert_with_key(|directory| {
+                    std::fs::read_to_string(verbatim_path(&directory.join(GIT_IGNORE_FILE)))
+                        .map(IgnoreRules::parse)
+                        .unwrap_or_default()
+                });
+
+                rules.matches(&parts[depth..length].join("/"), is_directory)
+            }) == Some(true)
+        })
+    }
+
+    fn record(&self, event: LoaderEvent) {
+        if let Some(event_log) = &self.event_log {
+            event_log.record(event);
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl SourceLoader for DirectorySourceLoader {
+    fn supports(&self, directory: &Path) -> bool {
+        self.explain(&directory) == InclusionDecision::Traversed
+    }
 
-    #[test]
-    fn test_directory() {
-        let root = format!(
-            "{}/examples/fixture/",
-            std::env::var("CARGO_MANIFEST_DIR").unwrap()
-        );
+    fn load(&self, directory: &Path) -> Result<SourceMap, Error> {
+        self.load_with_report(&directory).map(|(map, _)| map)
+    }
+}
 
-        let result = load_directories(root, vec!["src", "vendor/foo", "vendor/bar"]);
+/// A loader that chains multiple loaders.
+///
+/// A name is supported if any of the loaders supports it, and loading it merges
+/// the sources of every loader supporting it, in order.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ara_source::loader::CompositeSourceLoader;
+/// use ara_source::loader::DirectorySourceLoader;
+/// use ara_source::loader::FileSourceLoader;
+/// use ara_source::loader::SourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+///
+/// let loader = CompositeSourceLoader::new(vec![
+///     Box::new(FileSourceLoader::new(&root)),
+///     Box::new(DirectorySourceLoader::new(&root)),
+/// ]);
+///
+/// assert_eq!(loader.load(Path::new("src/main.ara")).unwrap().len(), 1);
+/// assert_eq!(loader.load(Path::new("vendor")).unwrap().len(), 2);
+/// assert!(loader.load(Path::new("missing")).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct CompositeSourceLoader {
+    pub loaders: Vec<Box<dyn SourceLoader>>,
+}
 
-        let map = result.unwrap();
+impl CompositeSourceLoader {
+    pub fn new(loaders: Vec<Box<dyn SourceLoader>>) -> CompositeSourceLoader {
+        CompositeSourceLoader { loaders }
+    }
 
-        assert_eq!(map.sources.len(), 3);
+    pub fn add(&mut self, loader: Box<dyn SourceLoader>) {
+        self.loaders.push(loader);
+    }
+}
 
-        assert_eq!(map.named("src/main.ara").unwrap().kind, SourceKind::Script);
-        assert_eq!(
-            map.named("vendor/foo/write_line.d.ara").unwrap().kind,
-            SourceKind::Definition
-        );
+impl SourceLoader for CompositeSourceLoader {
+    fn supports(&self, name: &Path) -> bool {
+        self.loaders.iter().any(|loader| loader.supports(name))
+    }
+
+    fn load(&self, name: &Path) -> Result<SourceMap, Error> {
+        if !self.supports(name) {
+            return Err(Error::InvalidSource(format!(
+                "source `{}` is not supported.",
+                name.to_string_lossy()
+            )));
+        }
+
+        let mut map = SourceMap::new(vec![]);
+        for loader in &self.loaders {
+            if loader.supports(name) {
+                loader.load_into(name, &mut map)?;
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+/// A loader of the files matching a glob pattern, relative to the root.
+///
+/// See `Glob` for the supported syntax, only the directory named by the literal
+/// prefix of the pattern is walked, following the rules of `DirectorySourceLoader`.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ara_source::loader::GlobSourceLoader;
+/// use ara_source::loader::SourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+///
+/// let loader = GlobSourceLoader::new(&root);
+///
+/// assert_eq!(loader.load(Path::new("src/**/*.ara")).unwrap().len(), 1);
+/// assert_eq!(loader.load(Path::new("vendor/*/*.d.ara")).unwrap().len(), 2);
+/// assert_eq!(loader.load_patterns(vec!["**/*.ara", "vendor/**"]).unwrap().len(), 3);
+/// ```
+#[derive(Debug)]
+pub struct GlobSourceLoader {
+    pub root: PathBuf,
+    loader: DirectorySourceLoader,
+}
+
+impl GlobSourceLoader {
+    pub fn new<T: AsRef<Path>>(root: &T) -> GlobSourceLoader {
+        let root = absolute_root(root.as_ref());
+
+        GlobSourceLoader {
+            loader: DirectorySourceLoader::new(&root),
+            root,
+        }
+    }
+
+    /// Call the given hook with each loaded source, see `FileSourceLoader::on_source_loaded`.
+    pub fn on_source_loaded<F>(mut self, hook: F) -> GlobSourceLoader
+    where
+        F: Fn(&mut Source) + Send + Sync + 'static,
+    {
+        self.loader = self.loader.on_source_loaded(hook);
+
+        self
+    }
+
+    /// Validate the content of each source when it is first read, see `FileSourceLoader::with_validator`.
+    pub fn with_validator<F>(mut self, validator: F) -> GlobSourceLoader
+    where
+        F: Fn(&Source, &str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.loader = self.loader.with_validator(validator);
+
+        self
+    }
+
+    /// Load a source map from the files matching any of the given patterns.
+    ///
+    /// Files matched by more than one pattern are loaded once, definitions are placed before scripts.
+    pub fn load_patterns<P: AsRef<str>>(&self, patterns: Vec<P>) -> Result<SourceMap, Error> {
+        let mut map = SourceMap::new(vec![]);
+        let mut loaded = HashSet::new();
+
+        for pattern in patterns {
+            let glob = Glob::new(pattern.as_ref());
+            let directory = self.root.join(glob.literal_prefix());
+
+            if let InclusionDecision::Traversed = self.loader.explain(&directory) {
+                walk(&self.loader, &directory, &mut |path, _| {
+                    let path = normalize(&path);
+                    let matches = relative_to(&self.root, &path)
+                        .is_some_and(|relative| glob.matches(relative.to_string_lossy()));
+
+                    if matches && !loaded.contains(&path) {
+                        self.loader.loader.load_into(&path, &mut map)?;
+                        loaded.insert(path);
+                    }
+
+                    Ok(())
+                })?;
+            }
+        }
+
+        map.sort_definitions_first();
+
+        Ok(map)
+    }
+}
+
+impl SourceLoader for GlobSourceLoader {
+    fn supports(&self, pattern: &Path) -> bool {
+        match pattern.to_str() {
+            Some(pattern) => {
+                let directory = self.root.join(Glob::new(pattern).literal_prefix());
+
+                matches!(
+                    self.loader.explain(&directory),
+                    InclusionDecision::Traversed
+                )
+            }
+            None => false,
+        }
+    }
+
+    fn load(&self, pattern: &Path) -> Result<SourceMap, Error> {
+        match pattern.to_str() {
+            Some(pattern) if self.supports(Path::new(pattern)) => self.load_patterns(vec![pattern]),
+            _ => Err(Error::InvalidSource(format!(
+                "pattern `{}` is not supported.",
+                pattern.to_string_lossy()
+            ))),
+        }
+    }
+}
+
+/// A loader of packages following the conventional layout, so simple projects need no configuration.
+///
+/// Within the package directory, `.ara` files under `src/` are loaded as scripts, all sources
+/// under `stubs/` are loaded as definitions, and `.d.ara` files are loaded as definitions anywhere.
+/// Other files are not loaded, directories are walked following the rules of `DirectorySourceLoader`.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ara_source::loader::ConventionSourceLoader;
+/// use ara_source::loader::SourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+///
+/// let map = ConventionSourceLoader::new(&root).load(Path::new(".")).unwrap();
+///
+/// assert_eq!(map.counts().scripts, 1);
+/// assert_eq!(map.counts().definitions, 2);
+/// ```
+#[derive(Debug)]
+pub struct ConventionSourceLoader {
+    pub root: PathBuf,
+    loader: DirectorySourceLoader,
+}
+
+impl ConventionSourceLoader {
+    pub fn new<T: AsRef<Path>>(root: &T) -> ConventionSourceLoader {
+        let root = absolute_root(root.as_ref());
+
+        ConventionSourceLoader {
+            loader: DirectorySourceLoader::new(&root),
+            root,
+        }
+    }
+
+    /// Call the given hook with each loaded source, see `FileSourceLoader::on_source_loaded`.
+    pub fn on_source_loaded<F>(mut self, hook: F) -> ConventionSourceLoader
+    where
+        F: Fn(&mut Source) + Send + Sync + 'static,
+    {
+        self.loader = self.loader.on_source_loaded(hook);
+
+        self
+    }
+
+    /// Validate the content of each source when it is first read, see `FileSourceLoader::with_validator`.
+    pub fn with_validator<F>(mut self, validator: F) -> ConventionSourceLoader
+    where
+        F: Fn(&Source, &str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.loader = self.loader.with_validator(validator);
+
+        self
+    }
+
+    /// Get the kind the given file is loaded as, relative to the given package directory.
+    fn kind_of(&self, package: &Path, file: &Path, kind: SourceKind) -> Option<SourceKind> {
+        let relative = normalize(&file);
+        let relative = relative.strip_prefix(normalize(&package)).ok()?;
+
+        if relative.starts_with(CONVENTION_STUBS_DIRECTORY) || kind == SourceKind::Definition {
+            Some(SourceKind::Definition)
+        } else if relative.starts_with(CONVENTION_SCRIPTS_DIRECTORY) {
+            Some(SourceKind::Script)
+        } else {
+            None
+        }
+    }
+}
+
+impl SourceLoader for ConventionSourceLoader {
+    fn supports(&self, package: &Path) -> bool {
+        self.loader.supports(package)
+    }
+
+    fn load(&self, package: &Path) -> Result<SourceMap, Error> {
+        if !self.supports(package) {
+            return Err(Error::InvalidSource(format!(
+                "package `{}` is not supported.",
+                package.to_string_lossy()
+            )));
+        }
+
+        let package = resolve(&self.root, package);
+        let mut map = SourceMap::new(vec![]);
+
+        walk(&self.loader, &package, &mut |file, kind| {
+            if let Some(kind) = self.kind_of(&package, file, kind) {
+                map.merge(&mut self.loader.loader.load_as(&file, kind)?)?;
+            }
+
+            Ok(())
+        })?;
+
+        map.sort_definitions_first();
+
+        Ok(map)
+    }
+}
+
+/// The type of a path, see `DirectorySourceLoader::explain_resolved`.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PathType {
+    pub(crate) is_directory: bool,
+    pub(crate) is_file: bool,
+}
+
+impl PathType {
+    /// Get the type of the given path, following symbolic links.
+    ///
+    /// A path that does not exist is neither a directory, nor a file.
+    pub(crate) fn of(path: &Path) -> PathType {
+        std::fs::metadata(verbatim_path(&path))
+            .map(|metadata| PathType::from(&metadata))
+            .unwrap_or_default()
+    }
+}
+
+impl From<&std::fs::Metadata> for PathType {
+    fn from(metadata: &std::fs::Metadata) -> PathType {
+        PathType {
+            is_directory: metadata.is_dir(),
+            is_file: metadata.is_file(),
+        }
+    }
+}
+
+/// Walk the given directory following the rules of the given loader, visiting each included file.
+pub(crate) fn walk(
+    loader: &DirectorySourceLoader,
+    directory: &Path,
+    visit: &mut dyn FnMut(&Path, SourceKind) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for entry in std::fs::read_dir(verbatim_path(&directory))? {
+        let path = directory.join(entry?.file_name());
+
+        match loader.explain(&path) {
+            InclusionDecision::Traversed => walk(loader, &path, visit)?,
+            InclusionDecision::Included(kind) => visit(&path, kind)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Make the given root absolute, so relative paths resolved against it are not resolved twice.
+pub(crate) fn absolute_root(root: &Path) -> PathBuf {
+    std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf())
+}
+
+pub(crate) fn resolve(root: &Path, path: &Path) -> PathBuf {
+    if path.is_relative() {
+        root.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn is_within(root: &Path, path: &Path) -> bool {
+    relative_to(&root, &path).is_some()
+}
+
+fn is_hidden(root: &Path, path: &Path) -> bool {
+    match relative_to(&root, &path) {
+        Some(relative) => relative
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.')),
+        None => false,
+    }
+}
+
+fn relative_parts(root: &Path, path: &Path) -> Option<Vec<String>> {
+    relative_to(&root, &path).map(|relative| {
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect()
+    })
+}
+
+pub(crate) fn read_error(file: &Path, error: std::io::Error) -> Error {
+    if error.kind() == ErrorKind::NotFound {
+        Error::SourceVanished(file.to_path_buf())
+    } else {
+        Error::IoError(error)
+    }
+}
+
+fn is_broken_symlink(path: &Path) -> bool {
+    let path = verbatim_path(&path);
+
+    match std::fs::symlink_metadata(&path) {
+        Ok(metadata) => metadata.file_type().is_symlink() && std::fs::metadata(&path).is_err(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_directory() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let result = load_directories(root, vec!["src", "vendor/foo", "vendor/bar"]);
+
+        let map = result.unwrap();
+
+        assert_eq!(map.sources().len(), 3);
+
+        assert_eq!(map.sources()[2].kind, SourceKind::Script);
+        assert_eq!(map.named("src/main.ara").unwrap().kind, SourceKind::Script);
+        assert_eq!(
+            map.named("vendor/foo/write_line.d.ara").unwrap().kind,
+            SourceKind::Definition
+        );
         assert_eq!(
             map.named("vendor/bar/bar.d.ara").unwrap().kind,
             SourceKind::Definition
         );
     }
+
+    #[test]
+    fn test_load_as() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loader = FileSourceLoader::new(&root);
+
+        let map = loader.load(Path::new("src/main.ara")).unwrap();
+        assert_eq!(map.named("src/main.ara").unwrap().kind, SourceKind::Script);
+
+        let map = loader
+            .load_as(&"src/main.ara", SourceKind::Definition)
+            .unwrap();
+        assert_eq!(
+            map.named("src/main.ara").unwrap().kind,
+            SourceKind::Definition
+        );
+    }
+
+    #[test]
+    fn test_pragmas() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let map = DirectorySourceLoader::new(&root)
+            .load(Path::new("src"))
+            .unwrap();
+        assert_eq!(map.named("src/main.ara").unwrap().pragmas, None);
+
+        let map = DirectorySourceLoader::new(&root)
+            .with_pragmas(true)
+            .load(Path::new("src"))
+            .unwrap();
+        assert_eq!(
+            map.named("src/main.ara").unwrap().pragmas,
+            Some(Default::default())
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_skip_unreadable() {
+        let root = TempDir::new("skip_unreadable");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.ara"), "function main(): void {}").unwrap();
+        std::fs::write(root.join("src/binary.ara"), [0xff, 0xfe, 0xfd]).unwrap();
+        std::os::unix::fs::symlink(root.join("src/missing.ara"), root.join("src/broken.ara"))
+            .unwrap();
+
+        let loader = DirectorySourceLoader::new(&root);
+        assert!(loader.load(Path::new("src")).is_err());
+
+        let loader = loader.with_skip_unreadable(true);
+        let (map, report) = loader.load_with_report(&"src").unwrap();
+
+        assert_eq!(map.sources().len(), 1);
+        assert!(map.named("src/main.ara").is_ok());
+
+        let skipped = report
+            .skipped
+            .into_iter()
+            .map(|skipped| (skipped.path, skipped.reason))
+            .collect::<Vec<(PathBuf, SkipReason)>>();
+
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(report.extensions["ara"].loaded, 1);
+        assert_eq!(report.extensions["ara"].skipped, 2);
+        assert!(skipped.contains(&(
+            root.join("src/binary.ara"),
+            SkipReason::Unreadable(std::io::ErrorKind::InvalidData)
+        )));
+        assert!(skipped.contains(&(root.join("src/broken.ara"), SkipReason::BrokenSymlink)));
+    }
+
+    #[test]
+    fn test_event_log() {
+        let root = format!("{}/examples/", std::env::var("CARGO_MANIFEST_DIR").unwrap());
+
+        let loader = DirectorySourceLoader::new(&root);
+        loader.load(Path::new("fixture/vendor/foo")).unwrap();
+        assert!(loader.events().is_empty());
+
+        let loader = DirectorySourceLoader::new(&root).with_event_log(10);
+        loader.load(Path::new(".")).unwrap();
+        assert!(loader.load(&std::env::temp_dir()).is_err());
+
+        let events = loader.events();
+        let root = PathBuf::from(root);
+
+        assert!(events.contains(&LoaderEvent::Loaded(root.join("./fixture/src/main.ara"))));
+        assert!(events.contains(&LoaderEvent::Excluded(
+            root.join("./simple.rs"),
+            InclusionDecision::UnsupportedExtension
+        )));
+        assert_eq!(
+            events.last(),
+            Some(&LoaderEvent::Excluded(
+                std::env::temp_dir(),
+                InclusionDecision::OutsideRoot
+            ))
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loader = DirectorySourceLoader::new(&root);
+
+        assert_eq!(loader.explain(&"src"), InclusionDecision::Traversed);
+        assert_eq!(
+            loader.explain(&"src/main.ara"),
+            InclusionDecision::Included(SourceKind::Script)
+        );
+        assert_eq!(
+            loader.explain(&"vendor/bar/bar.d.ara"),
+            InclusionDecision::Included(SourceKind::Definition)
+        );
+        assert_eq!(
+            loader.explain(&"src/missing.ara"),
+            InclusionDecision::NotFound
+        );
+        assert_eq!(loader.explain(&".."), InclusionDecision::OutsideRoot);
+        assert_eq!(loader.explain(&"src/../.."), InclusionDecision::OutsideRoot);
+        assert_eq!(
+            loader.explain(&"src/../vendor"),
+            InclusionDecision::Traversed
+        );
+
+        assert!(!loader.supports(Path::new("..")));
+        assert!(loader.load(Path::new("..")).is_err());
+
+        let map = loader.load(Path::new("vendor/../src")).unwrap();
+        assert!(map.named("src/main.ara").is_ok());
+    }
+
+    #[test]
+    fn test_relative_root() {
+        // tests run in the crate directory, so relative roots are within it.
+        let loader = FileSourceLoader::new(&".");
+        assert_eq!(
+            loader.explain(&"../secret/leak.ara"),
+            InclusionDecision::OutsideRoot
+        );
+        assert!(!loader.supports(Path::new("../secret/leak.ara")));
+
+        let loader = FileSourceLoader::new(&"examples/fixture/src");
+        assert_eq!(
+            loader.explain(&"../vendor/bar/bar.d.ara"),
+            InclusionDecision::OutsideRoot
+        );
+        assert!(loader.load(Path::new("../vendor/bar/bar.d.ara")).is_err());
+
+        let loader = FileSourceLoader::new(&".");
+
+        let map = loader
+            .load(Path::new("examples/fixture/src/main.ara"))
+            .unwrap();
+        assert!(map.named("examples/fixture/src/main.ara").is_ok());
+
+        let loader = DirectorySourceLoader::new(&"examples/fixture/src/..");
+        assert_eq!(loader.explain(&".."), InclusionDecision::OutsideRoot);
+        assert_eq!(
+            loader.explain(&"src/main.ara"),
+            InclusionDecision::Included(SourceKind::Script)
+        );
+
+        let map = loader.load(Path::new("src")).unwrap();
+        assert_eq!(map.named("src/main.ara").unwrap().kind, SourceKind::Script);
+    }
+
+    #[test]
+    fn test_vanished() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+        let vanished = PathBuf::from(&root).join("src/vanished");
+
+        let mut map = SourceMap::new(vec![]);
+        let mut report = LoadReport::default();
+
+        let loader = DirectorySourceLoader::new(&root);
+        let result = loader.load_directory(&vanished, &mut map, &mut report);
+        assert!(matches!(result, Err(Error::SourceVanished(path)) if path == vanished));
+
+        let loader = loader.with_skip_unreadable(true);
+        loader
+            .load_directory(&vanished, &mut map, &mut report)
+            .unwrap();
+        assert_eq!(report.skipped[0].path, vanished);
+        assert_eq!(report.skipped[0].reason, SkipReason::Vanished);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_non_utf8_path() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TempDir::new("non_utf8_path");
+        let file = root.join(OsStr::from_bytes(b"caf\xe9.ara"));
+        std::fs::write(&file, "function main(): void {}").unwrap();
+
+        let loader = FileSourceLoader::new(&root);
+        let strict = loader.load(&file);
+        let strict_directory = DirectorySourceLoader::new(&root).load(Path::new("."));
+
+        let loader = loader.with_lossy_origins(true);
+        let mut lossy = loader.load(&file).unwrap();
+
+        // the origin does not name the file, so it is refreshed from the file it was read from.
+        std::fs::write(&file, "function main(): int {}").unwrap();
+        let refreshed = lossy.refresh(&root);
+
+        assert!(matches!(strict, Err(Error::NonUtf8Path(path)) if path == file));
+        assert!(strict_directory.unwrap().is_empty());
+        assert_eq!(refreshed.unwrap().len(), 1);
+        assert_eq!(
+            lossy.named("caf\u{FFFD}.ara").unwrap().content,
+            "function main(): int {}"
+        );
+    }
+
+    #[test]
+    fn test_skip_hidden() {
+        let root = TempDir::new("skip_hidden");
+        std::fs::create_dir_all(root.join("src/.cache")).unwrap();
+        std::fs::write(root.join("src/main.ara"), "").unwrap();
+        std::fs::write(root.join("src/.hidden.ara"), "").unwrap();
+        std::fs::write(root.join("src/.cache/cached.ara"), "").unwrap();
+
+        let loader = DirectorySourceLoader::new(&root);
+        let hidden = loader.explain(&"src/.cache");
+        let skipped = loader.load(Path::new("src"));
+        let loaded = loader.with_skip_hidden(false).load(Path::new("src"));
+
+        assert_eq!(hidden, InclusionDecision::Hidden);
+        assert_eq!(skipped.unwrap().len(), 1);
+        assert_eq!(loaded.unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_dyn_source_loader() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loaders: Vec<(Box<dyn SourceLoader>, &str)> = vec![
+            (Box::new(FileSourceLoader::new(&root)), "src/main.ara"),
+            (Box::new(DirectorySourceLoader::new(&root)), "vendor"),
+        ];
+
+        let mut map = SourceMap::new(vec![]);
+        for (loader, name) in &loaders {
+            assert!(loader.supports(Path::new(name)));
+
+            loader.load_into(Path::new(name), &mut map).unwrap();
+        }
+
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn test_glob() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loader = GlobSourceLoader::new(&root);
+
+        let map = loader.load(Path::new("vendor/foo/*.ara")).unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(
+            map.sources()[0].origin.as_deref(),
+            Some("vendor/foo/write_line.d.ara")
+        );
+
+        let map = load_globs(&root, vec!["**/*.d.ara", "vendor/bar/**", "src/*.ara"]).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.sources()[2].origin.as_deref(), Some("src/main.ara"));
+
+        assert!(loader.load(Path::new("vendor/*.ara")).unwrap().is_empty());
+        assert!(!loader.supports(Path::new("missing/**/*.ara")));
+        assert!(!loader.supports(Path::new("../**/*.ara")));
+        assert!(loader.load(Path::new("missing/**/*.ara")).is_err());
+    }
+
+    #[test]
+    fn test_araignore() {
+        let root = TempDir::new("araignore");
+        std::fs::create_dir_all(root.join("src/generated")).unwrap();
+        std::fs::create_dir_all(root.join("tests/fixtures")).unwrap();
+        std::fs::write(root.join(".araignore"), "generated/\n/tests/fixtures\n").unwrap();
+        std::fs::write(root.join("src/main.ara"), "").unwrap();
+        std::fs::write(root.join("src/generated/foo.ara"), "").unwrap();
+        std::fs::write(root.join("tests/fixtures/bar.ara"), "").unwrap();
+        std::fs::write(root.join("tests/baz.ara"), "").unwrap();
+
+        assert_eq!(
+            DirectorySourceLoader::new(&root)
+                .load(Path::new("."))
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let loader = DirectorySourceLoader::new(&root).with_araignore(true);
+        let (map, report) = loader.load_with_report(&root).unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            loader.explain(&"src/generated/foo.ara"),
+            InclusionDecision::Ignored
+        );
+        assert!(report
+            .skipped
+            .iter()
+            .any(|skipped| { skipped.reason == SkipReason::Excluded(InclusionDecision::Ignored) }));
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let root = TempDir::new("gitignore");
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        std::fs::create_dir_all(root.join("vendor/foo/cache")).unwrap();
+        std::fs::write(root.join(".gitignore"), "build/\n*.tmp.ara\n").unwrap();
+        std::fs::write(root.join("vendor/.gitignore"), "cache/\n!keep.tmp.ara\n").unwrap();
+        std::fs::write(root.join("main.ara"), "").unwrap();
+        std::fs::write(root.join("main.tmp.ara"), "").unwrap();
+        std::fs::write(root.join("build/out.ara"), "").unwrap();
+        std::fs::write(root.join("vendor/foo/foo.d.ara"), "").unwrap();
+        std::fs::write(root.join("vendor/foo/keep.tmp.ara"), "").unwrap();
+        std::fs::write(root.join("vendor/foo/cache/bar.ara"), "").unwrap();
+
+        let loader = DirectorySourceLoader::new(&root);
+        assert_eq!(loader.load(Path::new(".")).unwrap().len(), 6);
+
+        let loader = loader.with_gitignore(true);
+        let map = loader.load(Path::new(".")).unwrap();
+
+        let mut origins = map
+            .iter()
+            .map(|source| source.origin.clone().unwrap())
+            .collect::<Vec<_>>();
+        origins.sort();
+
+        assert_eq!(
+            origins,
+            vec![
+                "main.ara",
+                "vendor/foo/foo.d.ara",
+                "vendor/foo/keep.tmp.ara"
+            ]
+        );
+        assert_eq!(loader.explain(&"build"), InclusionDecision::Ignored);
+        assert_eq!(
+            loader.explain(&"vendor/foo/cache/bar.ara"),
+            InclusionDecision::Ignored
+        );
+    }
+
+    #[test]
+    fn test_exclude_subtree() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loader = DirectorySourceLoader::new(&root)
+            .exclude_subtree(&"vendor/foo")
+            .exclude_subtree(&"src/main.ara");
+
+        let (map, report) = loader.load_with_report(&".").unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.sources()[0].name(), "vendor/bar/bar.d.ara");
+        assert!(report
+            .skipped
+            .iter()
+            .any(|skipped| skipped.reason == SkipReason::Excluded(InclusionDecision::Excluded)));
+
+        assert_eq!(
+            loader.explain(&"vendor/foo/write_line.d.ara"),
+            InclusionDecision::Excluded
+        );
+        assert_eq!(loader.explain(&"vendor"), InclusionDecision::Traversed);
+        assert!(loader.load(Path::new("vendor/foo")).is_err());
+    }
+
+    #[test]
+    fn test_load_file() {
+        let root = TempDir::new("load_file");
+        std::fs::create_dir_all(root.join("project/src/foo")).unwrap();
+        std::fs::write(root.join("project/.araignore"), "").unwrap();
+        std::fs::write(root.join("project/src/foo/main.ara"), "").unwrap();
+        std::fs::write(root.join("single.d.ara"), "").unwrap();
+
+        assert_eq!(
+            find_project_root(&root.join("project/src/foo")),
+            Some(root.join("project"))
+        );
+
+        let map = load_file(root.join("project/src/foo/main.ara")).unwrap();
+        assert_eq!(map.sources()[0].origin.as_deref(), Some("src/foo/main.ara"));
+        assert_eq!(map.sources()[0].kind, SourceKind::Script);
+
+        let map = load_file(root.join("project/src/../../single.d.ara")).unwrap();
+        assert_eq!(map.sources()[0].origin.as_deref(), Some("single.d.ara"));
+        assert_eq!(map.sources()[0].kind, SourceKind::Definition);
+
+        assert!(load_file(root.join("project/src/foo")).is_err());
+        assert!(load_file(root.join("missing.ara")).is_err());
+    }
+
+    #[test]
+    fn test_convention() {
+        let root = TempDir::new("convention");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("stubs/std")).unwrap();
+        std::fs::create_dir_all(root.join("tests")).unwrap();
+        std::fs::write(root.join("src/main.ara"), "").unwrap();
+        std::fs::write(root.join("src/foo.d.ara"), "").unwrap();
+        std::fs::write(root.join("stubs/std/io.ara"), "").unwrap();
+        std::fs::write(root.join("tests/foo.ara"), "").unwrap();
+        std::fs::write(root.join("tests/bar.d.ara"), "").unwrap();
+
+        let loader = ConventionSourceLoader::new(&root);
+        let map = loader.load(Path::new(".")).unwrap();
+
+        let mut sources = map
+            .iter()
+            .map(|source| (source.origin.clone().unwrap(), source.kind))
+            .collect::<Vec<_>>();
+        sources.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            sources,
+            vec![
+                ("src/foo.d.ara".to_string(), SourceKind::Definition),
+                ("src/main.ara".to_string(), SourceKind::Script),
+                ("stubs/std/io.ara".to_string(), SourceKind::Definition),
+                ("tests/bar.d.ara".to_string(), SourceKind::Definition),
+            ]
+        );
+
+        assert!(!loader.supports(Path::new("missing")));
+        assert!(loader.load(Path::new("src/main.ara")).is_err());
+    }
+
+    #[test]
+    fn test_validator() {
+        let root = TempDir::new("validator");
+        std::fs::write(root.join("foo.ara"), "function foo(): void {}").unwrap();
+        std::fs::write(root.join("bar.ara"), "function bar(): void {}\0").unwrap();
+
+        let loader = DirectorySourceLoader::new(&root)
+            .with_validator(|source, _| match source.kind {
+                SourceKind::Script => Ok(()),
+                SourceKind::Definition => Err("unexpected definition".to_string()),
+            })
+            .with_validator(|_, content| match content.contains('\0') {
+                true => Err("content contains a NUL byte".to_string()),
+                false => Ok(()),
+            });
+
+        match loader.load(Path::new(".")) {
+            Err(Error::InvalidContent(origin, message)) => {
+                assert_eq!(origin, "bar.ara");
+                assert_eq!(message, "content contains a NUL byte");
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        std::fs::remove_file(root.join("bar.ara")).unwrap();
+        assert_eq!(loader.load(Path::new(".")).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_definition_extension() {
+        let root = TempDir::new("definition_extension");
+        std::fs::write(root.join("food.ara"), "").unwrap();
+        std::fs::write(root.join("foo.d.ara"), "").unwrap();
+
+        let loader = FileSourceLoader::new(&root);
+        let food = loader.explain(&"food.ara");
+        let foo = loader.explain(&"foo.d.ara");
+
+        assert_eq!(food, InclusionDecision::Included(SourceKind::Script));
+        assert_eq!(foo, InclusionDecision::Included(SourceKind::Definition));
+    }
+
+    #[test]
+    fn test_on_source_loaded() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let loader = DirectorySourceLoader::new(&root)
+            .on_source_loaded(|source| source.set_display_name(format!("[{}]", source.name())))
+            .on_source_loaded(|source| {
+                if source.name().starts_with("[vendor/foo/") {
+                    source.kind = SourceKind::Script;
+                }
+            });
+
+        let map = loader.load(Path::new(".")).unwrap();
+
+        let source = map.named("vendor/foo/write_line.d.ara").unwrap();
+        assert_eq!(source.name(), "[vendor/foo/write_line.d.ara]");
+        assert_eq!(source.kind, SourceKind::Script);
+
+        let source = map.named("vendor/bar/bar.d.ara").unwrap();
+        assert_eq!(source.kind, SourceKind::Definition);
+    }
 }
@@ -0,0 +1,257 @@
+use std::path::Path;
+
+use crate::loader::absolute_root;
+use crate::path::file_uri;
+use crate::position::Span;
+use crate::source::Source;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// The unit in which the characters of a position are counted, as negotiated with an LSP client.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub enum PositionEncoding {
+    /// Characters are counted in UTF-8 bytes.
+    Utf8,
+
+    /// Characters are counted in UTF-16 code units, the default of the protocol.
+    #[default]
+    Utf16,
+
+    /// Characters are counted in unicode code points.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Get the encoding of the given protocol name ( `utf-8`, `utf-16` or `utf-32` ).
+    ///
+    /// If the name is not known, `None` is returned.
+    pub fn from_name(name: &str) -> Option<PositionEncoding> {
+        match name {
+            "utf-8" => Some(PositionEncoding::Utf8),
+            "utf-16" => Some(PositionEncoding::Utf16),
+            "utf-32" => Some(PositionEncoding::Utf32),
+            _ => None,
+        }
+    }
+
+    /// Get the protocol name of the encoding.
+    pub fn name(self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+            PositionEncoding::Utf32 => "utf-32",
+        }
+    }
+}
+
+/// A position as sent in LSP responses.
+///
+/// Unlike `Position`, the line and character are 0-based, and the character is counted in
+/// the negotiated `PositionEncoding`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+/// A range as sent in LSP responses, from its start position, to its ( exclusive ) end position.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+/// A location as sent in LSP responses: the URI of a file, and a range within it.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct LspLocation {
+    pub uri: String,
+    pub range: LspRange,
+}
+
+impl Source {
+    /// Get the LSP range of the given span, with characters counted in the given encoding.
+    ///
+    /// If the span is past the end of the content, or does not start and end on character
+    /// boundaries, `None` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::lsp::PositionEncoding;
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "function main(): void {\n    $é = 1;\n}\n");
+    /// let span = source.span_of(31..34).unwrap();
+    ///
+    /// let range = source.lsp_range(span, PositionEncoding::Utf16).unwrap();
+    /// assert_eq!((range.start.line, range.start.character), (1, 6));
+    ///
+    /// let range = source.lsp_range(span, PositionEncoding::Utf8).unwrap();
+    /// assert_eq!((range.start.line, range.start.character), (1, 7));
+    /// ```
+    pub fn lsp_range(&self, span: Span, encoding: PositionEncoding) -> Option<LspRange> {
+        Some(LspRange {
+            start: self.lsp_position(span.start.offset, encoding)?,
+            end: self.lsp_position(span.end.offset, encoding)?,
+        })
+    }
+
+    fn lsp_position(&self, offset: usize, encoding: PositionEncoding) -> Option<LspPosition> {
+        let index = self.line_index();
+        let (line, column) = index.offset_to_line_col(offset)?;
+        let prefix = self.content.get(offset + 1 - column..offset)?;
+
+        let character = if index.is_ascii(line) {
+            prefix.len()
+        } else {
+            match encoding {
+                PositionEncoding::Utf8 => prefix.len(),
+                PositionEncoding::Utf16 => prefix.encode_utf16().count(),
+                PositionEncoding::Utf32 => prefix.chars().count(),
+            }
+        };
+
+        Some(LspPosition {
+            line: u32::try_from(line - 1).ok()?,
+            character: u32::try_from(character).ok()?,
+        })
+    }
+}
+
+impl SourceMap {
+    /// Get the LSP location of the given span of the source with the given identifier, with
+    /// characters counted in the given encoding.
+    ///
+    /// The URI is the one of the file the source was read from, or, for sources that were
+    /// not read from a file, of its origin within the given root.
+    ///
+    /// If the source was removed, has no origin, or the span is not within its content
+    /// ( see `Source::lsp_range` ), `None` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// # if cfg!(unix) {
+    /// use ara_source::lsp::PositionEncoding;
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let map = SourceMap::single(Source::new(SourceKind::Script, "src/main.ara", "function main(): void {}"));
+    /// let id = map.ids().next().unwrap();
+    /// let span = map[id].span_of(9..13).unwrap();
+    ///
+    /// let location = map.to_lsp_location(&"/project", id, span, PositionEncoding::Utf16).unwrap();
+    ///
+    /// assert_eq!(location.uri, "file:///project/src/main.ara");
+    /// assert_eq!((location.range.start.line, location.range.start.character), (0, 9));
+    /// assert_eq!((location.range.end.line, location.range.end.character), (0, 13));
+    /// # }
+    /// ```
+    pub fn to_lsp_location<T: AsRef<Path>>(
+        &self,
+        root: &T,
+        id: SourceId,
+        span: Span,
+        encoding: PositionEncoding,
+    ) -> Option<LspLocation> {
+        let source = self.source(id)?;
+        let file = match source.stamp.file() {
+            Some(file) => file.to_path_buf(),
+            None => absolute_root(root.as_ref()).join(source.origin.as_ref()?),
+        };
+
+        Some(LspLocation {
+            uri: file_uri(&file)?,
+            range: source.lsp_range(span, encoding)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::FileSourceLoader;
+    use crate::loader::SourceLoader;
+    use crate::source::SourceKind;
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_lsp_range() {
+        // `😀` is 4 bytes, 2 utf-16 code units, and 1 code point.
+        let source = Source::inline(SourceKind::Script, "a😀b\r\nc😀d");
+
+        let range = |start, end, encoding| {
+            let range = source
+                .lsp_range(source.span_of(start..end).unwrap(), encoding)
+                .unwrap();
+
+            (
+                (range.start.line, range.start.character),
+                (range.end.line, range.end.character),
+            )
+        };
+
+        assert_eq!(range(5, 6, PositionEncoding::Utf8), ((0, 5), (0, 6)));
+        assert_eq!(range(5, 6, PositionEncoding::Utf16), ((0, 3), (0, 4)));
+        assert_eq!(range(5, 6, PositionEncoding::Utf32), ((0, 2), (0, 3)));
+        assert_eq!(range(8, 13, PositionEncoding::Utf16), ((1, 0), (1, 3)));
+        assert_eq!(range(13, 14, PositionEncoding::Utf32), ((1, 2), (1, 3)));
+
+        // offsets within a character have no position.
+        let span = source.span_of(0..2).unwrap();
+        assert!(source.lsp_range(span, PositionEncoding::Utf16).is_none());
+
+        assert_eq!(
+            PositionEncoding::from_name("utf-32"),
+            Some(PositionEncoding::Utf32)
+        );
+        assert_eq!(PositionEncoding::default().name(), "utf-16");
+        assert_eq!(PositionEncoding::from_name("utf-7"), None);
+    }
+
+    #[test]
+    fn test_to_lsp_location() {
+        let root = TempDir::new("to_lsp_location");
+        std::fs::write(root.join("main.ara"), "function main(): void {}").unwrap();
+
+        let mut map = FileSourceLoader::new(&root)
+            .load(Path::new("main.ara"))
+            .unwrap();
+        let main = map.ids().next().unwrap();
+        let inline = map
+            .add(Source::inline(
+                SourceKind::Script,
+                "function foo(): void {}\nfunction bar(): void {}",
+            ))
+            .unwrap();
+        let span = map[main].span_of(9..13).unwrap();
+
+        // sources read from a file are located in it, whatever the given root.
+        let location = map
+            .to_lsp_location(&"elsewhere", main, span, PositionEncoding::Utf8)
+            .unwrap();
+        assert_eq!(
+            location.uri,
+            file_uri(&absolute_root(&root).join("main.ara")).unwrap()
+        );
+        assert_eq!(
+            location.range.start,
+            LspPosition {
+                line: 0,
+                character: 9
+            }
+        );
+
+        assert!(map
+            .to_lsp_location(&root, inline, span, PositionEncoding::Utf8)
+            .is_none());
+
+        let past = map[inline].span_of(30..40).unwrap();
+        assert!(map
+            .to_lsp_location(&root, main, past, PositionEncoding::Utf8)
+            .is_none());
+    }
+}
@@ -0,0 +1,240 @@
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// File names that are reserved by Windows, regardless of their extension.
+pub const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The maximum length of a path on Windows, unless it uses the verbatim prefix.
+pub const WINDOWS_MAX_PATH: usize = 260;
+
+/// Check if the file name of the given path is reserved on Windows.
+///
+/// The check is case-insensitive, and ignores the extension, as Windows does.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::path::is_windows_reserved_name;
+///
+/// assert!(is_windows_reserved_name(&"src/con.ara"));
+/// assert!(is_windows_reserved_name(&"NUL.d.ara"));
+/// assert!(!is_windows_reserved_name(&"src/console.ara"));
+/// ```
+pub fn is_windows_reserved_name<T: AsRef<Path>>(path: &T) -> bool {
+    let name = match path.as_ref().file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Get a path that can be used to access the given path on the current platform.
+///
+/// On Windows, absolute paths longer than `WINDOWS_MAX_PATH` are given the
+/// verbatim (`\\?\`) prefix so they can be opened, on other platforms the path
+/// is returned as is.
+pub fn verbatim_path<T: AsRef<Path>>(path: &T) -> PathBuf {
+    let path = path.as_ref();
+
+    if cfg!(windows) {
+        let raw = path.to_string_lossy();
+
+        if path.is_absolute() && raw.len() >= WINDOWS_MAX_PATH && !raw.starts_with(r"\\?\") {
+            return if let Some(unc) = raw.strip_prefix(r"\\") {
+                PathBuf::from(format!(r"\\?\UNC\{}", unc.replace('/', r"\")))
+            } else {
+                PathBuf::from(format!(r"\\?\{}", raw.replace('/', r"\")))
+            };
+        }
+    }
+
+    path.to_path_buf()
+}
+
+/// Normalize the given path lexically, without accessing the file system.
+///
+/// `.` components are removed, and `..` components remove the preceding component.
+/// Leading `..` components of a relative path are kept, as there is nothing to remove.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::PathBuf;
+///
+/// use ara_source::path::normalize;
+///
+/// assert_eq!(normalize(&"/project/./src/../vendor"), PathBuf::from("/project/vendor"));
+/// assert_eq!(normalize(&"/project/.."), PathBuf::from("/"));
+/// assert_eq!(normalize(&"./../src/.."), PathBuf::from(".."));
+/// ```
+pub fn normalize<T: AsRef<Path>>(path: &T) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.as_ref().components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(Component::ParentDir),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Get the given path relative to the given root, after normalizing both.
+///
+/// If the path is not within the root, `None` is returned.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::PathBuf;
+///
+/// use ara_source::path::relative_to;
+///
+/// assert_eq!(relative_to(&"/project", &"/project/src/../main.ara"), Some(PathBuf::from("main.ara")));
+/// assert_eq!(relative_to(&".", &"src/main.ara"), Some(PathBuf::from("src/main.ara")));
+/// assert_eq!(relative_to(&".", &"../secret/main.ara"), None);
+/// ```
+pub fn relative_to<R: AsRef<Path>, P: AsRef<Path>>(root: &R, path: &P) -> Option<PathBuf> {
+    let path = normalize(path);
+    let relative = path.strip_prefix(normalize(root)).ok()?;
+
+    // an empty root is a prefix of any path, including ones above it or absolute ones.
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| relative.to_path_buf())
+}
+
+/// Get the `file://` URI of the given absolute path, as used by editors and language servers.
+///
+/// Bytes other than ascii letters, digits, `-`, `.`, `_`, `~`, `/` and `:` are percent-encoded,
+/// and on Windows, separators are replaced with `/`.
+///
+/// If the path is relative, or is not valid UTF-8, `None` is returned.
+///
+/// Example:
+///
+/// ```rust
+/// # if cfg!(unix) {
+/// use ara_source::path::file_uri;
+///
+/// assert_eq!(file_uri(&"/project/src/main.ara"), Some("file:///project/src/main.ara".to_string()));
+/// assert_eq!(file_uri(&"/project/my file.ara"), Some("file:///project/my%20file.ara".to_string()));
+/// assert_eq!(file_uri(&"src/main.ara"), None);
+/// # }
+/// ```
+pub fn file_uri<T: AsRef<Path>>(path: &T) -> Option<String> {
+    let path = path.as_ref();
+    if !path.is_absolute() {
+        return None;
+    }
+
+    let mut raw = path.to_str()?.to_string();
+    if cfg!(windows) {
+        // verbatim paths name the same file as their plain form, see `verbatim_path`.
+        if let Some(unc) = raw.strip_prefix(r"\\?\UNC\") {
+            raw = format!(r"\\{unc}");
+        } else if let Some(plain) = raw.strip_prefix(r"\\?\") {
+            raw = plain.to_string();
+        }
+
+        raw = raw.replace('\\', "/");
+    }
+
+    let mut uri = String::from("file:");
+    if raw.starts_with("//") {
+        // a UNC path already names its host.
+    } else if raw.starts_with('/') {
+        uri.push_str("//");
+    } else {
+        // a path starting with a drive letter.
+        uri.push_str("///");
+    }
+
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    Some(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_windows_reserved_name() {
+        assert!(is_windows_reserved_name(&"CON"));
+        assert!(is_windows_reserved_name(&"con.ara"));
+        assert!(is_windows_reserved_name(&"vendor/foo/Lpt1.d.ara"));
+        assert!(is_windows_reserved_name(&"aux .ara"));
+
+        assert!(!is_windows_reserved_name(&"COM10.ara"));
+        assert!(!is_windows_reserved_name(&"icon.ara"));
+        assert!(!is_windows_reserved_name(&"con/main.ara"));
+        assert!(!is_windows_reserved_name(&""));
+    }
+
+    #[test]
+    fn test_file_uri() {
+        let root = std::env::temp_dir().join("ara source");
+        let uri = file_uri(&root.join("é.ara")).unwrap();
+
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.ends_with("/ara%20source/%C3%A9.ara"));
+        assert!(!uri.contains('\\'));
+
+        assert_eq!(file_uri(&"main.ara"), None);
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(&"."), PathBuf::new());
+        assert_eq!(normalize(&"../../a/../b"), PathBuf::from("../../b"));
+        assert_eq!(normalize(&"a/../../b"), PathBuf::from("../b"));
+        assert_eq!(normalize(&"/../a"), PathBuf::from("/a"));
+
+        assert_eq!(relative_to(&".", &"."), Some(PathBuf::new()));
+        assert_eq!(relative_to(&"..", &"../a"), Some(PathBuf::from("a")));
+        assert_eq!(relative_to(&".", &".."), None);
+        assert_eq!(relative_to(&".", &"/etc/passwd"), None);
+        assert_eq!(relative_to(&"/project", &"/other"), None);
+    }
+
+    #[test]
+    fn test_verbatim_path() {
+        let short = std::env::temp_dir().join("main.ara");
+        assert_eq!(verbatim_path(&short), short);
+
+        let long = std::env::temp_dir()
+            .join("a".repeat(WINDOWS_MAX_PATH))
+            .join("main.ara");
+
+        if cfg!(windows) {
+            assert!(verbatim_path(&long).to_string_lossy().starts_with(r"\\?\"));
+        } else {
+            assert_eq!(verbatim_path(&long), long);
+        }
+    }
+}
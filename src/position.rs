@@ -0,0 +1,218 @@
+use std::ops::Range;
+
+/// A position within the content of a source, see `Source::position_at`.
+///
+/// The line and column are 1-based, the column and the offset are counted in bytes.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+/// A range of the content of a source, from its start position, to its ( exclusive ) end position.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+///
+/// let source = Source::inline(SourceKind::Script, "function main(): void {\n}\n");
+/// let span = source.span_of(9..13).unwrap();
+///
+/// assert_eq!(span.start.column, 10);
+/// assert_eq!(span.len(), 4);
+/// assert_eq!(&source.content[span.to_range()], "main");
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    /// Get the length of the span, in bytes.
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    /// Check if the span is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if the given offset is within the span.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start.offset <= offset && offset < self.end.offset
+    }
+
+    /// Get the byte range of the span.
+    pub fn to_range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// The lines enclosing a span of a source, with context lines around it, see `Source::snippet`.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+///
+/// let source = Source::inline(SourceKind::Script, "function main(): void {\n    foo();\n}\n");
+/// let span = source.span_of(28..31).unwrap();
+/// let snippet = source.snippet(span, 1).unwrap();
+///
+/// assert_eq!(snippet.lines.len(), 3);
+/// assert_eq!(snippet.lines[1].number, 2);
+/// assert_eq!(snippet.lines[1].content, "    foo();");
+/// assert_eq!(snippet.highlight_in(&snippet.lines[1]), Some(4..7));
+/// assert_eq!(snippet.highlight_in(&snippet.lines[0]), None);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Snippet<'a> {
+    pub lines: Vec<SnippetLine<'a>>,
+    /// The highlighted byte range of the content of the source.
+    pub highlight: Range<usize>,
+}
+
+/// A line of a snippet, without its line ending.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct SnippetLine<'a> {
+    /// The 1-based number of the line.
+    pub number: usize,
+    /// The byte range of the line in the content of the source.
+    pub range: Range<usize>,
+    pub content: &'a str,
+}
+
+impl Snippet<'_> {
+    /// Get the highlighted byte range within the given line, relative to its start.
+    ///
+    /// If the highlight does not cover the line, `None` is returned, a highlight of a line
+    /// ending is clamped to the end of its line.
+    pub fn highlight_in(&self, line: &SnippetLine<'_>) -> Option<Range<usize>> {
+        let highlight = &self.highlight;
+
+        // a highlight ending at the start of a line does not cover it, unless it is empty.
+        if highlight.start > line.range.end
+            || highlight.end < line.range.start
+            || (highlight.end == line.range.start && !highlight.is_empty())
+        {
+            return None;
+        }
+
+        let start = highlight.start.max(line.range.start);
+        let end = highlight.end.min(line.range.end);
+
+        Some(start - line.range.start..end - line.range.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::Source;
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_position_and_span() {
+        let source = Source::inline(SourceKind::Script, "foo\nbar");
+
+        assert_eq!(
+            source.position_at(5),
+            Some(Position {
+                line: 2,
+                column: 2,
+                offset: 5
+            })
+        );
+        assert_eq!(source.position_at(7).unwrap().column, 4);
+        assert_eq!(source.position_at(8), None);
+        assert_eq!(source.offset_of(2, 2), Some(5));
+        assert_eq!(source.offset_of(3, 1), None);
+
+        let span = source.span_of(2..5).unwrap();
+        assert_eq!(span.start.line, 1);
+        assert_eq!(span.end.line, 2);
+        assert!(span.contains(4));
+        assert!(!span.contains(5));
+        assert!(!span.is_empty());
+        assert!(source.span_of(Range { start: 5, end: 2 }).is_none());
+        assert!(source.span_of(2..9).is_none());
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let source = Source::inline(SourceKind::Script, "é😀a\nb");
+
+        assert_eq!(source.position_at_utf16(0).unwrap().column, 1);
+        assert_eq!(source.position_at_utf16(2).unwrap().column, 2);
+        assert_eq!(source.position_at_utf16(6).unwrap().column, 4);
+        assert_eq!(source.position_at_utf16(7).unwrap().column, 5);
+        assert_eq!(source.position_at_utf16(9).unwrap().column, 2);
+        assert_eq!(source.position_at_utf16(1), None);
+        assert_eq!(source.position_at_utf16(10), None);
+
+        assert_eq!(source.offset_of_utf16(1, 1), Some(0));
+        assert_eq!(source.offset_of_utf16(1, 4), Some(6));
+        assert_eq!(source.offset_of_utf16(1, 5), Some(7));
+        assert_eq!(source.offset_of_utf16(1, 3), None);
+        assert_eq!(source.offset_of_utf16(1, 6), None);
+        assert_eq!(source.offset_of_utf16(2, 2), Some(9));
+        assert_eq!(source.offset_of_utf16(3, 1), None);
+
+        for offset in [0, 2, 6, 7, 8, 9] {
+            let position = source.position_at_utf16(offset).unwrap();
+
+            assert_eq!(
+                source.offset_of_utf16(position.line, position.column),
+                Some(offset)
+            );
+        }
+    }
+
+    #[test]
+    fn test_snippet() {
+        let source = Source::inline(SourceKind::Script, "a\r\nbc\n\nd");
+
+        let snippet = source.snippet(source.span_of(3..5).unwrap(), 0).unwrap();
+        assert_eq!(
+            snippet.lines,
+            vec![SnippetLine {
+                number: 2,
+                range: 3..5,
+                content: "bc",
+            }]
+        );
+        assert_eq!(snippet.highlight_in(&snippet.lines[0]), Some(0..2));
+
+        let snippet = source.snippet(source.span_of(1..4).unwrap(), 5).unwrap();
+        assert_eq!(snippet.lines.len(), 4);
+        assert_eq!(snippet.lines[0].content, "a");
+        assert_eq!(snippet.lines[3].content, "d");
+        assert_eq!(snippet.highlight_in(&snippet.lines[0]), Some(1..1));
+        assert_eq!(snippet.highlight_in(&snippet.lines[1]), Some(0..1));
+        assert_eq!(snippet.highlight_in(&snippet.lines[2]), None);
+
+        let snippet = source.snippet(source.span_of(3..3).unwrap(), 0).unwrap();
+        assert_eq!(snippet.lines.len(), 1);
+        assert_eq!(snippet.highlight_in(&snippet.lines[0]), Some(0..0));
+
+        let snippet = source.snippet(source.span_of(5..6).unwrap(), 0).unwrap();
+        assert_eq!(snippet.lines.len(), 2);
+        assert_eq!(snippet.highlight_in(&snippet.lines[0]), Some(2..2));
+        assert_eq!(snippet.highlight_in(&snippet.lines[1]), None);
+
+        let span = source.span_of(0..1).unwrap();
+        let reversed = Span::new(span.end, span.start);
+        assert!(source.snippet(reversed, 0).is_none());
+    }
+}
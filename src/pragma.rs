@@ -0,0 +1,95 @@
+pub const PRAGMA_PREFIX: &str = "ara-";
+pub const IGNORE_FILE_PRAGMA: &str = "ignore-file";
+pub const LANGUAGE_VERSION_PRAGMA: &str = "language-version";
+
+/// The pragmas found in the header of a source.
+///
+/// The header is made of the comment and empty lines at the beginning of the
+/// source, a pragma is a header comment of the form `// ara-<name>` or
+/// `// ara-<name>: <value>`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Pragmas {
+    /// Whether the source is marked with `// ara-ignore-file`.
+    pub ignore_file: bool,
+
+    /// The value of the `// ara-language-version: <version>` pragma, if any.
+    pub language_version: Option<String>,
+
+    /// All pragmas, in order, including the ones above, by name without the `ara-` prefix.
+    pub all: Vec<(String, Option<String>)>,
+}
+
+impl Pragmas {
+    /// Scan the header of the given content for pragmas.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::pragma::Pragmas;
+    ///
+    /// let pragmas = Pragmas::scan("// ara-ignore-file\n// ara-language-version: 1.2\nfunction main(): void {}");
+    ///
+    /// assert!(pragmas.ignore_file);
+    /// assert_eq!(pragmas.language_version, Some("1.2".to_string()));
+    /// ```
+    pub fn scan(content: &str) -> Pragmas {
+        let mut pragmas = Pragmas::default();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let comment = match line.strip_prefix("//") {
+                Some(comment) => comment.trim(),
+                None => break,
+            };
+
+            let pragma = match comment.strip_prefix(PRAGMA_PREFIX) {
+                Some(pragma) => pragma,
+                None => continue,
+            };
+
+            let (name, value) = match pragma.split_once(':') {
+                Some((name, value)) => (name.trim(), Some(value.trim().to_string())),
+                None => (pragma, None),
+            };
+
+            match name {
+                IGNORE_FILE_PRAGMA => pragmas.ignore_file = true,
+                LANGUAGE_VERSION_PRAGMA => pragmas.language_version = value.clone(),
+                _ => {}
+            }
+
+            pragmas.all.push((name.to_string(), value));
+        }
+
+        pragmas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let pragmas = Pragmas::scan(
+            "\n// Copyright\n//ara-ignore-file\n  // ara-custom: a:b\n\nfunction main(): void {}\n// ara-language-version: 1.2\n",
+        );
+
+        assert!(pragmas.ignore_file);
+        assert_eq!(pragmas.language_version, None);
+        assert_eq!(
+            pragmas.all,
+            vec![
+                ("ignore-file".to_string(), None),
+                ("custom".to_string(), Some("a:b".to_string())),
+            ]
+        );
+
+        assert_eq!(Pragmas::scan(""), Pragmas::default());
+    }
+}
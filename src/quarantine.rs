@@ -0,0 +1,30 @@
+use std::time::Duration;
+use std::time::Instant;
+
+/// A quarantined source, that should be skipped by refresh loops until it expires.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Quarantine {
+    /// Why the source was quarantined, e.g. the last load or parse error.
+    pub reason: String,
+
+    /// When the quarantine expires, `None` if it never expires.
+    pub until: Option<Instant>,
+}
+
+impl Quarantine {
+    /// Create a quarantine with the given reason, expiring after the given duration.
+    ///
+    /// If the expiry is too far in the future to be represented ( e.g. `Duration::MAX` ),
+    /// the quarantine never expires.
+    pub fn new<T: Into<String>>(reason: T, duration: Duration) -> Quarantine {
+        Quarantine {
+            reason: reason.into(),
+            until: Instant::now().checked_add(duration),
+        }
+    }
+
+    /// Check if the quarantine has expired.
+    pub fn is_expired(&self) -> bool {
+        self.until.is_some_and(|until| Instant::now() >= until)
+    }
+}
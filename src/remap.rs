@@ -0,0 +1,114 @@
+use crate::source::Source;
+
+pub const SOURCE_DIRECTIVE: &str = "// @source ";
+
+/// A `// @source <origin>:<line>` marker found in a generated source.
+///
+/// The line following the marker corresponds to `original_line` in `origin`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LineDirective {
+    /// The (1-based) line of the marker in the generated source.
+    pub line: usize,
+
+    /// The origin of the original source.
+    pub origin: String,
+
+    /// The (1-based) line in the original source.
+    pub original_line: usize,
+}
+
+/// A table mapping lines of a generated source back to their original source.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RemapTable {
+    pub directives: Vec<LineDirective>,
+}
+
+impl RemapTable {
+    /// Scan the given source for `// @source <origin>:<line>` markers.
+    ///
+    /// Malformed markers are ignored.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::remap::RemapTable;
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::new(
+    ///     SourceKind::Script,
+    ///     "generated.ara",
+    ///     "// @source original.ara:42\nfunction foo(): void {}\nfunction bar(): void {}",
+    /// );
+    ///
+    /// let table = RemapTable::scan(&source);
+    ///
+    /// assert_eq!(table.resolve(1), None);
+    /// assert_eq!(table.resolve(2), Some(("original.ara", 42)));
+    /// assert_eq!(table.resolve(3), Some(("original.ara", 43)));
+    /// ```
+    pub fn scan(source: &Source) -> RemapTable {
+        let directives = source
+            .content
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let (origin, original_line) = line
+                    .trim()
+                    .strip_prefix(SOURCE_DIRECTIVE)?
+                    .trim()
+                    .rsplit_once(':')?;
+
+                Some(LineDirective {
+                    line: index + 1,
+                    origin: origin.to_string(),
+                    original_line: original_line.parse().ok()?,
+                })
+            })
+            .collect();
+
+        RemapTable { directives }
+    }
+
+    /// Resolve the given (1-based) line of the generated source to its origin and line.
+    ///
+    /// If the line is not preceded by any marker, `None` is returned.
+    pub fn resolve(&self, line: usize) -> Option<(&str, usize)> {
+        self.directives
+            .iter()
+            .rev()
+            .find(|directive| directive.line < line)
+            .map(|directive| {
+                (
+                    directive.origin.as_str(),
+                    directive.original_line + line - directive.line - 1,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_remap_table() {
+        let source = Source::new(
+            SourceKind::Script,
+            "generated.ara",
+            "function main(): void {}\n// @source foo.ara:10\nfoo\n// @source bar.ara\n  // @source bar.ara:3\nbar\nbaz\n",
+        );
+
+        let table = RemapTable::scan(&source);
+
+        assert_eq!(table.directives.len(), 2);
+        assert_eq!(table.resolve(1), None);
+        assert_eq!(table.resolve(2), None);
+        assert_eq!(table.resolve(3), Some(("foo.ara", 10)));
+        assert_eq!(table.resolve(4), Some(("foo.ara", 11)));
+        assert_eq!(table.resolve(6), Some(("bar.ara", 3)));
+        assert_eq!(table.resolve(7), Some(("bar.ara", 4)));
+    }
+}
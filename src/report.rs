@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::loader::InclusionDecision;
+use crate::source::Source;
+use crate::source::SourceKind;
+
+/// A summary of the sources of a source map.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceMapReport<'a> {
+    /// The number of `SourceKind::Script` sources.
+    pub scripts: usize,
+
+    /// The number of `SourceKind::Definition` sources.
+    pub definitions: usize,
+
+    /// The total size of the content of all sources, in bytes.
+    pub bytes: usize,
+
+    /// The largest sources, sorted by size in descending order.
+    pub largest: Vec<(&'a Source, usize)>,
+}
+
+impl<'a> SourceMapReport<'a> {
+    /// Create a report of the given sources, keeping the `top` largest sources.
+    pub fn new<I: IntoIterator<Item = &'a Source>>(sources: I, top: usize) -> SourceMapReport<'a> {
+        let mut report = SourceMapReport {
+            scripts: 0,
+            definitions: 0,
+            bytes: 0,
+            largest: vec![],
+        };
+
+        for source in sources {
+            match source.kind {
+                SourceKind::Script => report.scripts += 1,
+                SourceKind::Definition => report.definitions += 1,
+            }
+
+            report.bytes += source.content.len();
+            report.largest.push((source, source.content.len()));
+        }
+
+        report
+            .largest
+            .sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.name().cmp(b.name())));
+        report.largest.truncate(top);
+
+        report
+    }
+}
+
+/// The number of sources in a source map.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SourceCounts {
+    /// The number of `SourceKind::Script` sources.
+    pub scripts: usize,
+
+    /// The number of `SourceKind::Definition` sources.
+    pub definitions: usize,
+
+    /// The number of sources without an origin, of any kind.
+    pub inline: usize,
+}
+
+impl SourceCounts {
+    /// Count the given sources.
+    pub fn new<'a, I: IntoIterator<Item = &'a Source>>(sources: I) -> SourceCounts {
+        let mut counts = SourceCounts::default();
+
+        for source in sources {
+            counts.add(source);
+        }
+
+        counts
+    }
+
+    /// Count a source added to the source map.
+    pub(crate) fn add(&mut self, source: &Source) {
+        match source.kind {
+            SourceKind::Script => self.scripts += 1,
+            SourceKind::Definition => self.definitions += 1,
+        }
+
+        if source.origin.is_none() {
+            self.inline += 1;
+        }
+    }
+
+    /// Stop counting a source removed from the source map.
+    pub(crate) fn remove(&mut self, source: &Source) {
+        match source.kind {
+            SourceKind::Script => self.scripts -= 1,
+            SourceKind::Definition => self.definitions -= 1,
+        }
+
+        if source.origin.is_none() {
+            self.inline -= 1;
+        }
+    }
+}
+
+/// The reason a path was skipped while loading sources.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SkipReason {
+    /// The path could not be read, e.g. because permission was denied.
+    Unreadable(ErrorKind),
+
+    /// The path is a symbolic link pointing to a path that does not exist.
+    BrokenSymlink,
+
+    /// The path was removed between the directory scan and the read.
+    Vanished,
+
+    /// The path was excluded by the loader, see `InclusionDecision`.
+    Excluded(InclusionDecision),
+}
+
+/// A path that was skipped while loading sources.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SkippedSource {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// The number of loaded and skipped files with a given extension.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ExtensionCounts {
+    pub loaded: usize,
+    pub skipped: usize,
+}
+
+/// A report of a loader run.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LoadReport {
+    /// The paths that were skipped, in the order they were encountered.
+    pub skipped: Vec<SkippedSource>,
+
+    /// The number of loaded and skipped paths, per extension.
+    ///
+    /// The extension is the full extension chain of the file name ( e.g. `d.ara`
+    /// or `ara.txt` ), paths without an extension are counted under an empty extension.
+    pub extensions: BTreeMap<String, ExtensionCounts>,
+}
+
+impl LoadReport {
+    /// Record that the given path was loaded.
+    pub fn load<T: AsRef<Path>>(&mut self, path: &T) {
+        self.extensions
+            .entry(extension_of(path.as_ref()))
+            .or_default()
+            .loaded += 1;
+    }
+
+    /// Record that the given path was skipped for the given reason.
+    pub fn skip<T: AsRef<Path>>(&mut self, path: &T, reason: SkipReason) {
+        let path = path.as_ref();
+
+        self.extensions
+            .entry(extension_of(path))
+            .or_default()
+            .skipped += 1;
+
+        self.skipped.push(SkippedSource {
+            path: path.to_path_buf(),
+            reason,
+        });
+    }
+}
+
+fn extension_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .and_then(|name| {
+            name.trim_start_matches('.')
+                .split_once('.')
+                .map(|(_, extension)| extension.to_string())
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_report() {
+        let mut report = LoadReport::default();
+
+        report.load(&"src/main.ara");
+        report.load(&"src/foo.ara");
+        report.load(&"vendor/foo.d.ara");
+        report.skip(
+            &"src/notes.ara.txt",
+            SkipReason::Excluded(InclusionDecision::UnsupportedExtension),
+        );
+        report.skip(&"src/.gitignore", SkipReason::BrokenSymlink);
+
+        let counts = |loaded, skipped| ExtensionCounts { loaded, skipped };
+
+        assert_eq!(
+            report.extensions,
+            BTreeMap::from([
+                ("".to_string(), counts(0, 1)),
+                ("ara".to_string(), counts(2, 0)),
+                ("ara.txt".to_string(), counts(0, 1)),
+                ("d.ara".to_string(), counts(1, 0)),
+            ])
+        );
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(report.skipped[1].path, PathBuf::from("src/.gitignore"));
+    }
+
+    #[test]
+    fn test_report() {
+        let sources = vec![
+            Source::new(SourceKind::Script, "small.ara", "a"),
+            Source::new(SourceKind::Script, "large.ara", "abcdef"),
+            Source::new(SourceKind::Definition, "medium.d.ara", "abc"),
+        ];
+
+        let report = SourceMapReport::new(&sources, 2);
+
+        assert_eq!(report.scripts, 2);
+        assert_eq!(report.definitions, 1);
+        assert_eq!(report.bytes, 10);
+        assert_eq!(
+            report
+                .largest
+                .iter()
+                .map(|(source, size)| (source.name(), *size))
+                .collect::<Vec<(&str, usize)>>(),
+            vec![("large.ara", 6), ("medium.d.ara", 3)]
+        );
+    }
+}
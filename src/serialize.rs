@@ -0,0 +1,227 @@
+use serde::ser::SerializeSeq;
+use serde::ser::SerializeStruct;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+#[cfg(feature = "json")]
+use crate::error::Error;
+use crate::source::Source;
+use crate::SourceMap;
+
+/// A source map is serialized as the sequence of its sources, in order.
+///
+/// Identifiers are not serialized, the sources of a deserialized source map are identified
+/// in order, and package boundaries and quarantines are not kept.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+/// use ara_source::SourceMap;
+///
+/// let map = SourceMap::single(Source::new(SourceKind::Script, "main.ara", "function main(): void {}"));
+///
+/// let json = serde_json::to_string(&map).unwrap();
+/// let copy: SourceMap = serde_json::from_str(&json).unwrap();
+///
+/// assert_eq!(copy.sources(), map.sources());
+/// ```
+impl Serialize for SourceMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for SourceMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<SourceMap, D::Error> {
+        Vec::<Source>::deserialize(deserializer).map(SourceMap::new)
+    }
+}
+
+/// A view of a source map that is serialized without the content of its sources.
+///
+/// The content of sources deserialized from it is empty.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+/// use ara_source::SourceMap;
+///
+/// let map = SourceMap::single(Source::new(SourceKind::Script, "main.ara", "function main(): void {}"));
+///
+/// let json = serde_json::to_string(&map.without_content()).unwrap();
+/// let copy: SourceMap = serde_json::from_str(&json).unwrap();
+///
+/// assert_eq!(copy.sources()[0].origin, Some("main.ara".to_string()));
+/// assert_eq!(copy.sources()[0].content, "");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WithoutContent<'a>(&'a SourceMap);
+
+impl SourceMap {
+    /// Get a view of this source map that is serialized without the content of its sources.
+    pub fn without_content(&self) -> WithoutContent<'_> {
+        WithoutContent(self)
+    }
+}
+
+impl Serialize for WithoutContent<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut sequence = serializer.serialize_seq(Some(self.0.len()))?;
+
+        for source in self.0.iter() {
+            sequence.serialize_element(&SourceWithoutContent(source))?;
+        }
+
+        sequence.end()
+    }
+}
+
+#[cfg(feature = "json")]
+impl SourceMap {
+    /// Export this source map as JSON, with or without the content of its sources.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    /// use ara_source::SourceMap;
+    ///
+    /// let map = SourceMap::single(Source::new(SourceKind::Script, "main.ara", "function main(): void {}"));
+    ///
+    /// let copy = SourceMap::from_json(&map.to_json(true)).unwrap();
+    /// assert_eq!(copy.sources(), map.sources());
+    ///
+    /// let copy = SourceMap::from_json(&map.to_json(false)).unwrap();
+    /// assert_eq!(copy.sources()[0].content, "");
+    /// ```
+    pub fn to_json(&self, content: bool) -> String {
+        let json = if content {
+            serde_json::to_string(self)
+        } else {
+            serde_json::to_string(&self.without_content())
+        };
+
+        // sources only contain strings and enums, which always serialize.
+        json.unwrap()
+    }
+
+    /// Import a source map from JSON, see `to_json`.
+    ///
+    /// If the JSON is not a valid source map, `Error::InvalidSource` is returned.
+    pub fn from_json(json: &str) -> Result<SourceMap, Error> {
+        serde_json::from_str(json)
+            .map_err(|error| Error::InvalidSource(format!("invalid json: {error}")))
+    }
+}
+
+/// The JSON Schema of a serialized source map, with or without the content of its sources,
+/// so tools in other languages can validate it, or generate bindings for it.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::SourceMap;
+///
+/// let schema = serde_json::to_value(schemars::schema_for!(SourceMap)).unwrap();
+///
+/// assert_eq!(schema["type"], "array");
+/// ```
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for SourceMap {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "SourceMap".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        // the content of sources has a default, so sources without content are valid too.
+        Vec::<Source>::json_schema(generator)
+    }
+}
+
+struct SourceWithoutContent<'a>(&'a Source);
+
+impl Serialize for SourceWithoutContent<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut source = serializer.serialize_struct("Source", 4)?;
+
+        source.serialize_field("kind", &self.0.kind)?;
+        source.serialize_field("origin", &self.0.origin)?;
+        source.serialize_field("pragmas", &self.0.pragmas)?;
+        source.serialize_field("display_name", &self.0.display_name)?;
+
+        source.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pragma::Pragmas;
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_serialize() {
+        let mut source = Source::new(SourceKind::Definition, "foo.d.ara", "// ara-ignore-file");
+        source.pragmas = Some(Pragmas::scan(&source.content));
+        source.set_display_name("foo");
+
+        let map = SourceMap::new(vec![source, Source::inline(SourceKind::Script, "")]);
+
+        let copy: SourceMap = serde_json::from_str(&serde_json::to_string(&map).unwrap()).unwrap();
+        assert_eq!(copy.sources(), map.sources());
+        assert_eq!(
+            copy.ids().collect::<Vec<_>>(),
+            map.ids().collect::<Vec<_>>()
+        );
+
+        let json = serde_json::to_string(&map.without_content()).unwrap();
+        assert!(!json.contains("content"));
+
+        let copy: SourceMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(copy.len(), 2);
+        assert_eq!(copy.sources()[0].content, "");
+        assert_eq!(copy.sources()[0].name(), "foo");
+        assert_eq!(copy.sources()[0].pragmas, map.sources()[0].pragmas);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json() {
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Definition, "foo.d.ara", "foo"),
+            Source::inline(SourceKind::Script, "bar"),
+        ]);
+
+        assert_eq!(
+            SourceMap::from_json(&map.to_json(true)).unwrap().sources(),
+            map.sources()
+        );
+        assert_eq!(
+            map.to_json(false),
+            r#"[{"kind":"Definition","origin":"foo.d.ara","pragmas":null,"display_name":null},{"kind":"Script","origin":null,"pragmas":null,"display_name":null}]"#
+        );
+        assert!(SourceMap::from_json("{}").is_err());
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(SourceMap)).unwrap();
+        let source = &schema["$defs"]["Source"];
+
+        assert_eq!(schema["type"], "array");
+        assert_eq!(schema["items"]["$ref"], "#/$defs/Source");
+        assert_eq!(source["required"], serde_json::json!(["kind"]));
+        assert!(source["properties"]["content"].is_object());
+        assert!(source["properties"].get("line_index").is_none());
+        assert!(schema["$defs"]["SourceKind"].is_object());
+    }
+}
@@ -1,6 +1,28 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::Metadata;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::path::MAIN_SEPARATOR;
+use std::time::SystemTime;
+
+use crate::line_index::LineIndex;
+use crate::line_index::LineIndexCache;
+use crate::position::Position;
+use crate::position::Snippet;
+use crate::position::SnippetLine;
+use crate::position::Span;
+use crate::pragma::Pragmas;
+
 pub const DEFAULT_NAME: &str = "<unknown>";
+pub const PREVIEW_ELLIPSIS: char = '…';
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum SourceKind {
     /// A definition is a piece of code that is not executed, but can be used
     /// to define foriegn symbols ( e.g from PHP ).
@@ -10,11 +32,85 @@ pub enum SourceKind {
     Script,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// The components of the origin of a source, see `Source::origin_components`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct OriginComponents<'a> {
+    /// The directory of the origin, empty if the origin has no directory.
+    pub directory: &'a str,
+
+    /// The file name, without its extensions.
+    pub stem: &'a str,
+
+    /// The full extension chain, without the leading dot ( e.g. `d.ara` ), empty if there is none.
+    pub extension: &'a str,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Source {
     pub kind: SourceKind,
     pub origin: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub content: String,
+
+    /// The pragmas found in the header of the source, if it was scanned.
+    pub pragmas: Option<Pragmas>,
+
+    /// A friendly name shown instead of the origin, see `Source::name`.
+    pub display_name: Option<String>,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    line_index: LineIndexCache,
+
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) stamp: FileStamp,
+}
+
+/// The file a source was read from, with its size and modification time when it was read,
+/// ignored when comparing and hashing sources.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileStamp {
+    file: Option<PathBuf>,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub(crate) fn new(file: &Path, metadata: &Metadata) -> FileStamp {
+        FileStamp {
+            file: Some(file.to_path_buf()),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// Get the file the source was read from, if it was read from a file.
+    pub(crate) fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Check if the file is unchanged, according to its current metadata.
+    ///
+    /// Without a modification time, the file is always considered changed.
+    pub(crate) fn matches(&self, metadata: &Metadata) -> bool {
+        self.file.is_some()
+            && self.modified.is_some()
+            && self.size == metadata.len()
+            && self.modified == metadata.modified().ok()
+    }
+}
+
+impl PartialEq for FileStamp {
+    fn eq(&self, _: &FileStamp) -> bool {
+        true
+    }
+}
+
+impl Eq for FileStamp {}
+
+impl Hash for FileStamp {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
 }
 
 /// A source.
@@ -61,6 +157,10 @@ impl Source {
             kind,
             origin: Some(origin.into()),
             content: content.into(),
+            pragmas: None,
+            display_name: None,
+            line_index: LineIndexCache::default(),
+            stamp: FileStamp::default(),
         }
     }
 
@@ -83,12 +183,17 @@ impl Source {
             kind,
             origin: None,
             content: content.into(),
+            pragmas: None,
+            display_name: None,
+            line_index: LineIndexCache::default(),
+            stamp: FileStamp::default(),
         }
     }
 
     /// Get the name of the source.
     ///
-    /// If the source has an origin, the origin is returned.
+    /// If the source has a display name, the display name is returned.
+    /// Otherwise, if the source has an origin, the origin is returned.
     /// Otherwise, the default name is returned.
     ///
     /// Example:
@@ -104,9 +209,488 @@ impl Source {
     /// assert_eq!(source.name(), "<unknown>");
     /// ```
     pub fn name(&self) -> &str {
-        match self.origin {
-            Some(ref origin) => origin,
-            None => DEFAULT_NAME,
+        match (&self.display_name, &self.origin) {
+            (Some(display_name), _) => display_name,
+            (None, Some(origin)) => origin,
+            (None, None) => DEFAULT_NAME,
+        }
+    }
+
+    /// Set the display name of the source.
+    ///
+    /// The display name is only used by `name`, the origin is still used for
+    /// lookups and paths.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let mut source = Source::new(SourceKind::Script, "foo.generated.ara", "");
+    /// source.set_display_name("generated from macro `bar` in foo.ara");
+    ///
+    /// assert_eq!(source.name(), "generated from macro `bar` in foo.ara");
+    /// assert_eq!(source.origin, Some("foo.generated.ara".to_string()));
+    /// ```
+    pub fn set_display_name<T: Into<String>>(&mut self, display_name: T) {
+        self.display_name = Some(display_name.into());
+    }
+
+    /// Get the length of the content of the source, in bytes.
+    ///
+    /// The content is read when the source is loaded, so this never performs any IO.
+    pub fn len_bytes(&self) -> usize {
+        self.content.len()
+    }
+
+    /// Get the number of lines of the source, as displayed by an editor.
+    ///
+    /// Lines are separated by `\n`, so empty content has a single line, and
+    /// a trailing newline starts a new, empty, line.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// assert_eq!(Source::inline(SourceKind::Script, "").len_lines(), 1);
+    /// assert_eq!(Source::inline(SourceKind::Script, "foo\r\nbar").len_lines(), 2);
+    /// assert_eq!(Source::inline(SourceKind::Script, "foo\nbar\n").len_lines(), 3);
+    /// ```
+    pub fn len_lines(&self) -> usize {
+        self.content.bytes().filter(|byte| *byte == b'\n').count() + 1
+    }
+
+    /// Get the line index of the source, computed on first use and cached.
+    ///
+    /// If the length of the content changed since the index was cached, a fresh index is
+    /// computed, but edits that keep the length are not detected, call `reset_line_index`
+    /// after modifying the content directly. Sources borrowed mutably from a `SourceMap`
+    /// have their line index reset already.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "function main(): void {\n}\n");
+    ///
+    /// assert_eq!(source.line_index().line_count(), 3);
+    /// assert_eq!(source.line_index().offset_to_line_col(24), Some((2, 1)));
+    /// ```
+    pub fn line_index(&self) -> Cow<'_, LineIndex> {
+        self.line_index.get_or_init(&self.content)
+    }
+
+    /// Discard the cached line index of the source, see `line_index`.
+    pub fn reset_line_index(&mut self) {
+        self.line_index.reset();
+    }
+
+    /// Get the position of the given byte offset.
+    ///
+    /// The end of the content is a valid offset, if the offset is past it, `None` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "function main(): void {\n}\n");
+    /// let position = source.position_at(24).unwrap();
+    ///
+    /// assert_eq!((position.line, position.column, position.offset), (2, 1, 24));
+    /// ```
+    pub fn position_at(&self, offset: usize) -> Option<Position> {
+        let (line, column) = self.line_index().offset_to_line_col(offset)?;
+
+        Some(Position {
+            line,
+            column,
+            offset,
+        })
+    }
+
+    /// Get the byte offset of the given (1-based) line and column.
+    ///
+    /// If the line does not exist, or the column is past its end, `None` is returned.
+    pub fn offset_of(&self, line: usize, column: usize) -> Option<usize> {
+        self.line_index().line_col_to_offset(line, column)
+    }
+
+    /// Get the position of the given byte offset, with a column counted in UTF-16 code units,
+    /// as used by the language server protocol.
+    ///
+    /// The line and column are 1-based. If the offset is past the end of the content, or is
+    /// not on a character boundary, `None` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "$a = '😀';");
+    ///
+    /// assert_eq!(source.position_at(11).unwrap().column, 12);
+    /// assert_eq!(source.position_at_utf16(11).unwrap().column, 10);
+    /// assert_eq!(source.offset_of_utf16(1, 10), Some(11));
+    /// ```
+    pub fn position_at_utf16(&self, offset: usize) -> Option<Position> {
+        let index = self.line_index();
+        let (line, column) = index.offset_to_line_col(offset)?;
+        let prefix = self.content.get(offset + 1 - column..offset)?;
+
+        let column = if index.is_ascii(line) {
+            column
+        } else {
+            prefix.encode_utf16().count() + 1
+        };
+
+        Some(Position {
+            line,
+            column,
+            offset,
+        })
+    }
+
+    /// Get the byte offset of the given (1-based) line and UTF-16 column, see `position_at_utf16`.
+    ///
+    /// If the line does not exist, or the column is past its end, or within a character,
+    /// `None` is returned.
+    pub fn offset_of_utf16(&self, line: usize, column: usize) -> Option<usize> {
+        let index = self.line_index();
+        let range = index.line_range(line)?;
+
+        if index.is_ascii(line) {
+            return index.line_col_to_offset(line, column);
+        }
+
+        let mut units = column.checked_sub(1)?;
+
+        for (offset, character) in self.content.get(range.clone())?.char_indices() {
+            if units == 0 {
+                return Some(range.start + offset);
+            }
+
+            units = units.checked_sub(character.len_utf16())?;
+        }
+
+        (units == 0).then_some(range.end)
+    }
+
+    /// Get the span of the given byte range.
+    ///
+    /// If the range is reversed, or ends past the end of the content, `None` is returned.
+    pub fn span_of(&self, range: Range<usize>) -> Option<Span> {
+        if range.start > range.end {
+            return None;
+        }
+
+        Some(Span::new(
+            self.position_at(range.start)?,
+            self.position_at(range.end)?,
+        ))
+    }
+
+    /// Get the lines enclosing the given span, with up to `context_lines` lines before and after it.
+    ///
+    /// If the span is reversed, or ends past the end of the content, `None` is returned.
+    ///
+    /// See `Snippet` for an example.
+    pub fn snippet(&self, span: Span, context_lines: usize) -> Option<Snippet<'_>> {
+        let range = span.to_range();
+        if range.start > range.end {
+            return None;
+        }
+
+        let index = self.line_index();
+        let (first, _) = index.offset_to_line_col(range.start)?;
+        let (last, _) = index.offset_to_line_col(range.end)?;
+
+        let first = first.saturating_sub(context_lines).max(1);
+        let last = last.saturating_add(context_lines).min(index.line_count());
+
+        // the index may be stale, so lines are sliced without assuming their ranges are valid.
+        let lines = (first..=last)
+            .map(|number| {
+                let range = index.line_range(number)?;
+
+                Some(SnippetLine {
+                    number,
+                    content: self.content.get(range.clone())?,
+                    range,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Snippet {
+            lines,
+            highlight: range,
+        })
+    }
+
+    /// Get the digest of the content of the source.
+    ///
+    /// Unlike the fingerprint, the digest does not depend on the kind or origin of the source,
+    /// so it is the same for a moved or duplicated file.
+    pub fn content_digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.content.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Get the fingerprint of the source.
+    ///
+    /// The fingerprint is a digest of the kind, origin, and content of the source,
+    /// it is stable for the same build of the library, and can be used to detect
+    /// changes between two loads of the same source.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let a = Source::new(SourceKind::Script, "main.ara", "function main(): void {}");
+    /// let b = Source::new(SourceKind::Script, "main.ara", "function main(): void {}");
+    /// let c = Source::new(SourceKind::Script, "main.ara", "function main(): int {}");
+    ///
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_ne!(a.fingerprint(), c.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint_with(|content| Cow::Borrowed(content))
+    }
+
+    /// Get the fingerprint of the source, computed over a canonical form of its content.
+    ///
+    /// The given normalizer maps the content to its canonical form, so edits that
+    /// do not change the canonical form ( e.g. whitespace-only edits ) do not change
+    /// the fingerprint.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use std::borrow::Cow;
+    ///
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let a = Source::new(SourceKind::Script, "main.ara", "function main(): void {}");
+    /// let b = Source::new(SourceKind::Script, "main.ara", "function  main():void {}\n");
+    ///
+    /// fn normalize(content: &str) -> Cow<str> {
+    ///     Cow::Owned(content.split_whitespace().collect::<Vec<&str>>().join(""))
+    /// }
+    ///
+    /// assert_ne!(a.fingerprint(), b.fingerprint());
+    /// assert_eq!(a.fingerprint_with(normalize), b.fingerprint_with(normalize));
+    /// ```
+    pub fn fingerprint_with<F>(&self, normalizer: F) -> u64
+    where
+        F: for<'a> Fn(&'a str) -> Cow<'a, str>,
+    {
+        let mut hasher = DefaultHasher::new();
+
+        self.kind.hash(&mut hasher);
+        self.origin.hash(&mut hasher);
+        normalizer(&self.content).hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    /// Iterate over the content of the source in chunks of at most `chunk_size` bytes.
+    ///
+    /// Chunks always end on a char boundary, so a chunk may be shorter than `chunk_size`,
+    /// or longer if a single char does not fit in `chunk_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "aé€b");
+    ///
+    /// assert_eq!(source.chunks(2).collect::<Vec<&str>>(), vec!["a", "é", "€", "b"]);
+    /// assert_eq!(source.chunks(4).collect::<Vec<&str>>(), vec!["aé", "€b"]);
+    /// ```
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = &str> {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+
+        let mut remaining = self.content.as_str();
+
+        std::iter::from_fn(move || {
+            if remaining.is_empty() {
+                return None;
+            }
+
+            let mut end = chunk_size.min(remaining.len());
+            while !remaining.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            if end == 0 {
+                end = remaining.chars().next().map_or(0, char::len_utf8);
+            }
+
+            let (chunk, rest) = remaining.split_at(end);
+            remaining = rest;
+
+            Some(chunk)
+        })
+    }
+
+    /// Get a short excerpt of the source, suitable for logs and error messages.
+    ///
+    /// The excerpt is the first non-empty line of the source, truncated to `max_len`
+    /// chars, and followed by `PREVIEW_ELLIPSIS` if anything was left out.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::inline(SourceKind::Script, "\nfunction main(): void {}\n");
+    ///
+    /// assert_eq!(source.preview(100), "function main(): void {}");
+    /// assert_eq!(source.preview(8), "function…");
+    ///
+    /// let source = Source::inline(SourceKind::Script, "function main(): void {\n}\n");
+    ///
+    /// assert_eq!(source.preview(100), "function main(): void {…");
+    /// ```
+    pub fn preview(&self, max_len: usize) -> String {
+        let content = self.content.trim();
+        let line = content.lines().next().unwrap_or_default().trim_end();
+
+        let mut preview = line.chars().take(max_len).collect::<String>();
+        if preview.len() < content.len() {
+            preview.push(PREVIEW_ELLIPSIS);
         }
+
+        preview
+    }
+
+    /// Scan the header of the source for pragmas, and store them in the source.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let mut source = Source::inline(SourceKind::Script, "// ara-ignore-file\n");
+    /// assert_eq!(source.pragmas, None);
+    ///
+    /// assert!(source.scan_pragmas().ignore_file);
+    /// assert!(source.pragmas.unwrap().ignore_file);
+    /// ```
+    pub fn scan_pragmas(&mut self) -> &Pragmas {
+        self.pragmas.insert(Pragmas::scan(&self.content))
+    }
+
+    /// Get the directory, file stem, and full extension chain of the origin of the source.
+    ///
+    /// The extension chain starts at the first dot of the file name, ignoring leading
+    /// dots of hidden files, so `foo.d.ara` has the stem `foo` and the extension `d.ara`.
+    ///
+    /// If the source has no origin, `None` is returned.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use ara_source::source::OriginComponents;
+    /// use ara_source::source::Source;
+    /// use ara_source::source::SourceKind;
+    ///
+    /// let source = Source::new(SourceKind::Definition, "vendor/foo/write_line.d.ara", "");
+    ///
+    /// assert_eq!(
+    ///     source.origin_components(),
+    ///     Some(OriginComponents {
+    ///         directory: "vendor/foo",
+    ///         stem: "write_line",
+    ///         extension: "d.ara",
+    ///     })
+    /// );
+    ///
+    /// let source = Source::inline(SourceKind::Script, "");
+    ///
+    /// assert_eq!(source.origin_components(), None);
+    /// ```
+    pub fn origin_components(&self) -> Option<OriginComponents<'_>> {
+        let origin = self.origin.as_deref()?;
+
+        let (directory, name) = match origin.rfind(['/', MAIN_SEPARATOR]) {
+            Some(index) => (&origin[..index], &origin[index + 1..]),
+            None => ("", origin),
+        };
+
+        let hidden = name.len() - name.trim_start_matches('.').len();
+        let (stem, extension) = match name[hidden..].find('.') {
+            Some(index) => (&name[..hidden + index], &name[hidden + index + 1..]),
+            None => (name, ""),
+        };
+
+        Some(OriginComponents {
+            directory,
+            stem,
+            extension,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_components() {
+        let source = |origin| Source::new(SourceKind::Script, origin, "");
+        let components = |directory, stem, extension| {
+            Some(OriginComponents {
+                directory,
+                stem,
+                extension,
+            })
+        };
+
+        assert_eq!(
+            source("main.ara").origin_components(),
+            components("", "main", "ara")
+        );
+        assert_eq!(
+            source("src/food.ara").origin_components(),
+            components("src", "food", "ara")
+        );
+        assert_eq!(
+            source("a/b/foo.d.ara").origin_components(),
+            components("a/b", "foo", "d.ara")
+        );
+        assert_eq!(
+            source("src/.hidden.ara").origin_components(),
+            components("src", ".hidden", "ara")
+        );
+        assert_eq!(
+            source("notes.ara.txt").origin_components(),
+            components("", "notes", "ara.txt")
+        );
+        assert_eq!(
+            source("src/Makefile").origin_components(),
+            components("src", "Makefile", "")
+        );
     }
 }
@@ -0,0 +1,41 @@
+use std::ops::Deref;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// A temporary directory for tests, removed when dropped, even if the test fails.
+#[derive(Debug)]
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create an empty temporary directory, unique to the given name and to the process.
+    pub(crate) fn new(name: &str) -> TempDir {
+        let path =
+            std::env::temp_dir().join(format!("ara_source_test_{name}_{}", std::process::id()));
+
+        // a previous run may have been killed before it could clean up.
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        TempDir(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
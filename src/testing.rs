@@ -0,0 +1,99 @@
+use crate::loader::ARA_DEFINTION_EXTENSION;
+use crate::source::SourceKind;
+
+/// Build an in-memory source map from origins and contents, for tests.
+///
+/// The kind of each source is inferred from its origin, as `FileSourceLoader` does,
+/// unless it is given explicitly with `(definition)` or `(script)`.
+///
+/// Panics if two sources have the same origin.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::source::SourceKind;
+/// use ara_source::source_map;
+///
+/// let map = source_map! {
+///     "src/main.ara" => "function main(): void {}",
+///     "vendor/foo.d.ara" => "function foo(): void;",
+///     "vendor/bar.ara" (definition) => "function bar(): void;",
+/// };
+///
+/// assert_eq!(map.len(), 3);
+/// assert_eq!(map.named("src/main.ara").unwrap().kind, SourceKind::Script);
+/// assert_eq!(map.named("vendor/foo.d.ara").unwrap().kind, SourceKind::Definition);
+/// assert_eq!(map.named("vendor/bar.ara").unwrap().kind, SourceKind::Definition);
+/// ```
+#[macro_export]
+macro_rules! source_map {
+    ($($origin:literal $(($kind:ident))? => $content:expr),* $(,)?) => {{
+        let mut map = $crate::SourceMap::new(vec![]);
+
+        map.add_all(vec![$(
+            $crate::source::Source::new(
+                $crate::source_map!(@kind $origin $($kind)?),
+                $origin,
+                $content,
+            )
+        ),*])
+        .expect("source_map! does not accept duplicate origins.");
+
+        map
+    }};
+    (@kind $origin:literal definition) => {
+        $crate::source::SourceKind::Definition
+    };
+    (@kind $origin:literal script) => {
+        $crate::source::SourceKind::Script
+    };
+    (@kind $origin:literal) => {
+        $crate::testing::kind_of($origin)
+    };
+}
+
+/// Infer the kind of a source from its origin, see `source_map!`.
+#[doc(hidden)]
+pub fn kind_of(origin: &str) -> SourceKind {
+    let is_definition = origin
+        .strip_suffix(ARA_DEFINTION_EXTENSION)
+        .is_some_and(|stem| stem.ends_with('.'));
+
+    if is_definition {
+        SourceKind::Definition
+    } else {
+        SourceKind::Script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map_macro() {
+        let map = source_map! {
+            "a.ara" => "a",
+            "b.d.ara" => String::from("b"),
+            "c.d.ara" (script) => "c"
+        };
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.sources()[0].kind, SourceKind::Script);
+        assert_eq!(map.sources()[1].kind, SourceKind::Definition);
+        assert_eq!(map.sources()[1].content, "b");
+        assert_eq!(map.sources()[2].kind, SourceKind::Script);
+
+        assert!(source_map! {}.is_empty());
+        assert_eq!(kind_of("d.ara"), SourceKind::Script);
+    }
+
+    #[test]
+    #[should_panic(expected = "source_map! does not accept duplicate origins.")]
+    fn test_source_map_macro_duplicate() {
+        source_map! {
+            "a.ara" => "",
+            "a.ara" => "",
+        };
+    }
+}
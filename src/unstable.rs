@@ -0,0 +1,16 @@
+//! Experimental subsystems, enabled by the `unstable` feature.
+//!
+//! Unlike the rest of the crate, these modules are not covered by semver guarantees, and
+//! may change or be removed in any release. The `notify`, `tokio` and `rkyv` features
+//! enable the subsystems depending on them, and imply `unstable`.
+
+#[cfg(feature = "rkyv")]
+pub mod archive;
+#[cfg(feature = "tokio")]
+pub mod async_loader;
+pub mod cache;
+pub mod federation;
+pub mod handle;
+pub mod lazy;
+#[cfg(feature = "notify")]
+pub mod watch;
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use memmap2::Mmap;
+use rkyv::rancor;
+use rkyv::Archive;
+use rkyv::Deserialize;
+use rkyv::Serialize;
+
+use crate::error::Error;
+use crate::loader::read_error;
+use crate::path::verbatim_path;
+use crate::source::Source;
+use crate::source::SourceKind;
+use crate::SourceMap;
+
+#[derive(Archive, Serialize, Deserialize)]
+struct Record {
+    definition: bool,
+    origin: Option<String>,
+    content: String,
+    // the size and modification time of the file of the origin when it was archived.
+    size: Option<u64>,
+    modified: Option<u64>,
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+struct Records {
+    records: Vec<Record>,
+}
+
+/// Write the given source map to the given archive file, see `SourceArchive`.
+///
+/// The size and modification time of the file of each source origin within the given root
+/// are archived along with it, to validate the source when the archive is opened.
+pub fn write_archive<R: AsRef<Path>, F: AsRef<Path>>(
+    map: &SourceMap,
+    root: &R,
+    file: &F,
+) -> Result<(), Error> {
+    let records = Records {
+        records: map
+            .iter()
+            .map(|source| {
+                let (size, modified) = stamp(root.as_ref(), source.origin.as_deref());
+
+                Record {
+                    definition: source.kind == SourceKind::Definition,
+                    origin: source.origin.clone(),
+                    content: source.content.clone(),
+                    size,
+                    modified,
+                }
+            })
+            .collect(),
+    };
+
+    let bytes = rkyv::to_bytes::<rancor::Error>(&records).map_err(invalid)?;
+
+    std::fs::write(verbatim_path(file), bytes)?;
+
+    Ok(())
+}
+
+/// A source map archive, memory-mapped and read in place, without deserialization.
+///
+/// The archive is validated once when it is opened, and every source whose file changed
+/// since it was archived, or no longer exists, is reported as stale.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::unstable::archive::write_archive;
+/// use ara_source::unstable::archive::SourceArchive;
+/// use ara_source::loader::load_directories;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+/// let file = std::env::temp_dir().join(format!("ara_source_archive_{}", std::process::id()));
+///
+/// let map = load_directories(&root, vec!["src", "vendor"]).unwrap();
+/// write_archive(&map, &root, &file).unwrap();
+///
+/// // SAFETY: the archive file is not modified while it is open.
+/// let archive = unsafe { SourceArchive::open(&file, &root) }.unwrap();
+///
+/// assert_eq!(archive.len(), 3);
+/// assert_eq!(archive.origin(2), Some("src/main.ara"));
+/// assert_eq!(archive.content(2), Some(map.sources()[2].content.as_str()));
+/// assert!(archive.stale().is_empty());
+/// # drop(archive);
+/// # std::fs::remove_file(&file).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SourceArchive {
+    mmap: Mmap,
+    stale: Vec<usize>,
+}
+
+impl SourceArchive {
+    /// Open the given archive file, validating its sources against the given root.
+    ///
+    /// If the file is not a valid archive, `Error::InvalidSource` is returned.
+    ///
+    /// # Safety
+    ///
+    /// The file is memory-mapped, and only validated here, so it must not be modified or
+    /// truncated, by this or any other process, until the returned archive is dropped.
+    pub unsafe fn open<F: AsRef<Path>, R: AsRef<Path>>(
+        file: &F,
+        root: &R,
+    ) -> Result<SourceArchive, Error> {
+        let file = file.as_ref();
+        let handle = File::open(verbatim_path(&file)).map_err(|error| read_error(file, error))?;
+
+        // SAFETY: the mapping is read-only, and the caller guarantees the file is not modified.
+        let mmap = unsafe { Mmap::map(&handle)? };
+
+        let records = rkyv::access::<ArchivedRecords, rancor::Error>(&mmap).map_err(invalid)?;
+        let stale = records
+            .records
+            .iter()
+            .enumerate()
+            .filter(|(_, record)| match record.origin.as_ref() {
+                Some(origin) => {
+                    let archived = (
+                        record.size.as_ref().map(|size| size.to_native()),
+                        record
+                            .modified
+                            .as_ref()
+                            .map(|modified| modified.to_native()),
+                    );
+
+                    archived.0.is_none() || archived != stamp(root.as_ref(), Some(origin.as_str()))
+                }
+                None => false,
+            })
+            .map(|(position, _)| position)
+            .collect();
+
+        Ok(SourceArchive { mmap, stale })
+    }
+
+    /// Get the number of archived sources.
+    pub fn len(&self) -> usize {
+        self.records().len()
+    }
+
+    /// Check if the archive has no sources.
+    pub fn is_empty(&self) -> bool {
+        self.records().is_empty()
+    }
+
+    /// Get the kind of the source at the given position.
+    pub fn kind(&self, position: usize) -> Option<SourceKind> {
+        self.records().get(position).map(|record| {
+            if record.definition {
+                SourceKind::Definition
+            } else {
+                SourceKind::Script
+            }
+        })
+    }
+
+    /// Get the origin of the source at the given position, if it has one.
+    pub fn origin(&self, position: usize) -> Option<&str> {
+        self.records()
+            .get(position)?
+            .origin
+            .as_ref()
+            .map(|origin| origin.as_str())
+    }
+
+    /// Get the content of the source at the given position.
+    pub fn content(&self, position: usize) -> Option<&str> {
+        self.records()
+            .get(position)
+            .map(|record| record.content.as_str())
+    }
+
+    /// Get the positions of the sources whose files changed since they were archived.
+    pub fn stale(&self) -> &[usize] {
+        &self.stale
+    }
+
+    /// Copy the archived sources into a source map, stale sources included.
+    pub fn to_source_map(&self) -> SourceMap {
+        SourceMap::new(
+            (0..self.len())
+                .map(|position| {
+                    let kind = self.kind(position).unwrap();
+                    let content = self.content(position).unwrap();
+
+                    match self.origin(position) {
+                        Some(origin) => Source::new(kind, origin, content),
+                        None => Source::inline(kind, content),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn records(&self) -> &rkyv::vec::ArchivedVec<ArchivedRecord> {
+        // SAFETY: the archive was validated when it was opened, and the caller of `open`
+        // guarantees the file was not modified since.
+        unsafe { &rkyv::access_unchecked::<ArchivedRecords>(&self.mmap).records }
+    }
+}
+
+fn stamp(root: &Path, origin: Option<&str>) -> (Option<u64>, Option<u64>) {
+    let metadata = match origin.map(|origin| std::fs::metadata(verbatim_path(&root.join(origin)))) {
+        Some(Ok(metadata)) => metadata,
+        _ => return (None, None),
+    };
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .and_then(|modified| u64::try_from(modified.as_nanos()).ok());
+
+    (Some(metadata.len()), modified)
+}
+
+fn invalid(error: rancor::Error) -> Error {
+    Error::InvalidSource(format!("invalid archive: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_source_archive() {
+        let root = TempDir::new("archive");
+        std::fs::write(root.join("main.ara"), "function main(): void {}").unwrap();
+        std::fs::write(root.join("foo.d.ara"), "function foo(): void;").unwrap();
+
+        let map = SourceMap::new(vec![
+            Source::new(SourceKind::Definition, "foo.d.ara", "function foo(): void;"),
+            Source::new(SourceKind::Script, "main.ara", "function main(): void {}"),
+            Source::inline(SourceKind::Script, "inline"),
+        ]);
+
+        let file = root.join("sources.archive");
+        write_archive(&map, &root, &file).unwrap();
+
+        // SAFETY: the archive file is only modified once the archive is dropped.
+        let archive = unsafe { SourceArchive::open(&file, &root) }.unwrap();
+        assert_eq!(archive.len(), 3);
+        assert_eq!(archive.kind(0), Some(SourceKind::Definition));
+        assert_eq!(archive.origin(2), None);
+        assert_eq!(archive.content(2), Some("inline"));
+        assert_eq!(archive.kind(3), None);
+        assert!(archive.stale().is_empty());
+        assert_eq!(archive.to_source_map().sources(), map.sources());
+        drop(archive);
+
+        std::fs::write(root.join("main.ara"), "function main(): int {}").unwrap();
+        std::fs::remove_file(root.join("foo.d.ara")).unwrap();
+        let archive = unsafe { SourceArchive::open(&file, &root) }.unwrap();
+        assert_eq!(archive.stale(), &[0, 1]);
+        drop(archive);
+
+        std::fs::write(&file, "not an archive").unwrap();
+        assert!(unsafe { SourceArchive::open(&file, &root) }.is_err());
+    }
+}
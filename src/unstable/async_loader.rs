@@ -0,0 +1,295 @@
+use std::future::Future;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Error;
+use crate::ignore::GIT_IGNORE_FILE;
+use crate::loader::read_error;
+use crate::loader::resolve;
+use crate::loader::DirectorySourceLoader;
+use crate::loader::FileSourceLoader;
+use crate::loader::InclusionDecision;
+use crate::loader::PathType;
+use crate::path::verbatim_path;
+use crate::source::SourceKind;
+use crate::SourceMap;
+
+/// An asynchronous loader of sources, reading content with `tokio::fs`.
+///
+/// This mirrors `SourceLoader` for use within async runtimes, e.g. language servers
+/// and build daemons, where blocking reads would stall other tasks.
+pub trait AsyncSourceLoader: std::fmt::Debug {
+    /// Check if the given name is supported by this loader.
+    ///
+    /// If `true` is returned, `load` *MUST NOT* return `Error::InvalidSource`.
+    fn supports(&self, name: &Path) -> bool;
+
+    /// Load a source map from the given name.
+    ///
+    /// If the source name is not valid, `Error::InvalidSource` is returned.
+    fn load(&self, name: &Path) -> impl Future<Output = Result<SourceMap, Error>> + Send;
+}
+
+/// An asynchronous variant of `FileSourceLoader`.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ara_source::unstable::async_loader::AsyncFileSourceLoader;
+/// use ara_source::unstable::async_loader::AsyncSourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+/// let loader = AsyncFileSourceLoader::new(&root);
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let map = runtime.block_on(loader.load(Path::new("src/main.ara"))).unwrap();
+///
+/// assert_eq!(map.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct AsyncFileSourceLoader {
+    loader: FileSourceLoader,
+}
+
+impl AsyncFileSourceLoader {
+    pub fn new<T: AsRef<Path>>(root: &T) -> AsyncFileSourceLoader {
+        AsyncFileSourceLoader {
+            loader: FileSourceLoader::new(root),
+        }
+    }
+
+    /// Get the root of this loader.
+    pub fn root(&self) -> &Path {
+        &self.loader.root
+    }
+
+    /// Load a source from the given file, forcing the given kind.
+    pub async fn load_as<T: AsRef<Path>>(
+        &self,
+        file: &T,
+        kind: SourceKind,
+    ) -> Result<SourceMap, Error> {
+        read(&self.loader, file.as_ref(), Some(kind)).await
+    }
+}
+
+/// Create an asynchronous loader from a configured `FileSourceLoader`, keeping its options and hooks.
+impl From<FileSourceLoader> for AsyncFileSourceLoader {
+    fn from(loader: FileSourceLoader) -> AsyncFileSourceLoader {
+        AsyncFileSourceLoader { loader }
+    }
+}
+
+impl AsyncSourceLoader for AsyncFileSourceLoader {
+    fn supports(&self, file: &Path) -> bool {
+        matches!(self.loader.explain(&file), InclusionDecision::Included(_))
+    }
+
+    async fn load(&self, file: &Path) -> Result<SourceMap, Error> {
+        read(&self.loader, file, None).await
+    }
+}
+
+/// An asynchronous variant of `DirectorySourceLoader`.
+///
+/// Directories are walked with the rules of the wrapped loader, and every error is returned,
+/// regardless of `DirectorySourceLoader::with_skip_unreadable`.
+///
+/// Example:
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ara_source::unstable::async_loader::AsyncDirectorySourceLoader;
+/// use ara_source::unstable::async_loader::AsyncSourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+/// let loader = AsyncDirectorySourceLoader::new(&root);
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let map = runtime.block_on(loader.load(Path::new("vendor"))).unwrap();
+///
+/// assert_eq!(map.len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct AsyncDirectorySourceLoader {
+    loader: DirectorySourceLoader,
+}
+
+impl AsyncDirectorySourceLoader {
+    pub fn new<T: AsRef<Path>>(root: &T) -> AsyncDirectorySourceLoader {
+        AsyncDirectorySourceLoader {
+            loader: DirectorySourceLoader::new(root),
+        }
+    }
+
+    /// Get the root of this loader.
+    pub fn root(&self) -> &Path {
+        &self.loader.root
+    }
+}
+
+impl AsyncDirectorySourceLoader {
+    /// Explain the given resolved path, see `DirectorySourceLoader::explain`, without blocking.
+    async fn explain(&self, path: &Path) -> InclusionDecision {
+        // tokio reads files on its blocking thread pool.
+        for directory in self.loader.uncached_ignore_directories(path) {
+            let content =
+                tokio::fs::read_to_string(verbatim_path(&directory.join(GIT_IGNORE_FILE)))
+                    .await
+                    .ok();
+
+            self.loader.cache_ignore_file(directory, content);
+        }
+
+        let path_type = path_type(path).await;
+
+        self.loader.explain_resolved(path, || path_type)
+    }
+}
+
+/// Create an asynchronous loader from a configured `DirectorySourceLoader`, keeping its options and hooks.
+impl From<DirectorySourceLoader> for AsyncDirectorySourceLoader {
+    fn from(loader: DirectorySourceLoader) -> AsyncDirectorySourceLoader {
+        AsyncDirectorySourceLoader { loader }
+    }
+}
+
+impl AsyncSourceLoader for AsyncDirectorySourceLoader {
+    fn supports(&self, directory: &Path) -> bool {
+        self.loader.explain(&directory) == InclusionDecision::Traversed
+    }
+
+    async fn load(&self, directory: &Path) -> Result<SourceMap, Error> {
+        // pick up changes to .gitignore files made since the previous load.
+        self.loader.clear_ignore_cache();
+
+        let directory = resolve(&self.loader.root, directory);
+        if self.explain(&directory).await != InclusionDecision::Traversed {
+            return Err(Error::InvalidSource(format!(
+                "source `{}` is not supported.",
+                directory.to_string_lossy()
+            )));
+        }
+
+        let mut map = SourceMap::new(vec![]);
+        let mut directories: Vec<PathBuf> = vec![directory];
+
+        while let Some(directory) = directories.pop() {
+            let mut entries = tokio::fs::read_dir(verbatim_path(&directory))
+                .await
+                .map_err(|error| read_error(&directory, error))?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = directory.join(entry.file_name());
+
+                match self.explain(&path).await {
+                    InclusionDecision::Traversed => directories.push(path),
+                    InclusionDecision::Included(_) => {
+                        map.merge(&mut read(&self.loader.loader, &path, None).await?)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        map.sort_definitions_first();
+
+        Ok(map)
+    }
+}
+
+async fn read(
+    loader: &FileSourceLoader,
+    file: &Path,
+    kind: Option<SourceKind>,
+) -> Result<SourceMap, Error> {
+    let file = resolve(&loader.root, file);
+    let metadata = tokio::fs::metadata(verbatim_path(&file)).await.ok();
+    let is_file = metadata.as_ref().is_some_and(|metadata| metadata.is_file());
+    let (file, kind) = loader.prepare(&file, kind, loader.explain_resolved(&file, || is_file))?;
+    let content = tokio::fs::read_to_string(verbatim_path(&file))
+        .await
+        .map_err(|error| read_error(&file, error))?;
+
+    loader.finish(&file, kind, content, metadata.as_ref())
+}
+
+async fn path_type(path: &Path) -> PathType {
+    tokio::fs::metadata(verbatim_path(&path))
+        .await
+        .map(|metadata| PathType::from(&metadata))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_async_loaders() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let loader = AsyncDirectorySourceLoader::from(
+            DirectorySourceLoader::new(&root).on_source_loaded(|source| {
+                source.set_display_name("async");
+            }),
+        );
+
+        let map = runtime.block_on(loader.load(Path::new("."))).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.sources()[2].kind, SourceKind::Script);
+        assert!(map.iter().all(|source| source.name() == "async"));
+
+        assert!(runtime.block_on(loader.load(Path::new("missing"))).is_err());
+
+        let loader = AsyncFileSourceLoader::new(&root);
+        let map = runtime
+            .block_on(loader.load_as(&"src/main.ara", SourceKind::Definition))
+            .unwrap();
+        assert_eq!(map.sources()[0].kind, SourceKind::Definition);
+
+        assert!(!loader.supports(Path::new("vendor")));
+        assert!(runtime.block_on(loader.load(Path::new("vendor"))).is_err());
+    }
+
+    #[test]
+    fn test_async_gitignore() {
+        let root = TempDir::new("async_gitignore");
+        std::fs::create_dir_all(root.join("vendor/foo/cache")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.tmp.ara\n").unwrap();
+        std::fs::write(root.join("vendor/.gitignore"), "cache/\n").unwrap();
+        std::fs::write(root.join("vendor/foo/foo.d.ara"), "").unwrap();
+        std::fs::write(root.join("vendor/foo/foo.tmp.ara"), "").unwrap();
+        std::fs::write(root.join("vendor/foo/cache/bar.ara"), "").unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let loader = AsyncDirectorySourceLoader::from(
+            DirectorySourceLoader::new(&root).with_gitignore(true),
+        );
+
+        let map = runtime
+            .block_on(loader.load(Path::new("vendor/foo")))
+            .unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.sources()[0].name(), "vendor/foo/foo.d.ara");
+
+        // ignore files are read again on each load.
+        std::fs::write(root.join(".gitignore"), "").unwrap();
+        let map = runtime.block_on(loader.load(Path::new("vendor"))).unwrap();
+        assert_eq!(map.len(), 2);
+    }
+}
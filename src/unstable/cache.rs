@@ -0,0 +1,626 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs::Metadata;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::error::Error;
+use crate::loader::read_error;
+use crate::loader::resolve;
+use crate::loader::walk;
+use crate::loader::DirectorySourceLoader;
+use crate::loader::SourceLoader;
+use crate::path::normalize;
+use crate::path::verbatim_path;
+use crate::source::SourceKind;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// The leading bytes of a cache file, changed whenever the format changes.
+pub const CACHE_MAGIC: &[u8; 8] = b"ARASRC\x00\x02";
+
+/// The coarsest modification time granularity of supported file systems ( e.g. FAT ).
+///
+/// A file modified within this duration of being cached may be modified again without
+/// changing its modification time, so its content is hashed to validate it.
+const MODIFIED_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// The extension of cache files, see `cache_file`.
+pub const CACHE_EXTENSION: &str = "cache";
+
+/// Get the path of the cache file of the given root within the given cache directory.
+///
+/// The file name is derived from the root, so one cache directory can hold the caches of
+/// several roots.
+pub fn cache_file<D: AsRef<Path>, R: AsRef<Path>>(directory: &D, root: &R) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    normalize(root).hash(&mut hasher);
+
+    directory
+        .as_ref()
+        .join(format!("{:016x}.{}", hasher.finish(), CACHE_EXTENSION))
+}
+
+/// The number of sources reused from a cache, and read from disk, by `SourceCache::load`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct CacheStats {
+    /// The number of sources whose cached content was still valid.
+    pub reused: usize,
+
+    /// The number of sources that were missing from the cache, or stale, and were read.
+    pub read: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CacheEntry {
+    kind: SourceKind,
+    size: u64,
+    // the modification time as seconds and nanoseconds since the unix epoch, if available.
+    modified: Option<(u64, u32)>,
+    // when the content was last read or hashed, in the same unit.
+    checked: Option<(u64, u32)>,
+    // the digest of the content, to validate files whose modification time is ambiguous.
+    digest: u64,
+    content: String,
+}
+
+/// A persisted cache of the sources of a root, keyed by origin.
+///
+/// Each cached source is validated against the size and modification time of its file,
+/// only files that changed are read again. Files modified shortly before they were cached
+/// are hashed as well, as a later change within the granularity of their modification time
+/// would keep both.
+///
+/// Hashing can be deferred with `with_lazy_verification`, so a warm start only reads the
+/// cache file, and the hashed files are verified later by `verify`.
+///
+/// A missing, corrupted, or outdated cache file is treated as an empty cache.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::unstable::cache::cache_file;
+/// use ara_source::unstable::cache::SourceCache;
+/// use ara_source::loader::DirectorySourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+/// let file = cache_file(&std::env::temp_dir(), &root);
+///
+/// let loader = DirectorySourceLoader::new(&root);
+/// let mut cache = SourceCache::open(&file, &root);
+///
+/// let (map, _) = cache.load(&loader, &".").unwrap();
+/// assert_eq!(map.len(), 3);
+///
+/// cache.save(&file).unwrap();
+///
+/// let (map, stats) = SourceCache::open(&file, &root).load(&loader, &".").unwrap();
+/// assert_eq!(map.len(), 3);
+/// assert_eq!(stats.reused, 3);
+/// # std::fs::remove_file(&file).unwrap();
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceCache {
+    root: PathBuf,
+    entries: HashMap<String, CacheEntry>,
+    // whether ambiguous entries are reused without being hashed, until `verify` is called.
+    lazy_verification: bool,
+    // the origins of the entries that were reused without being hashed.
+    unverified: HashSet<String>,
+}
+
+impl SourceCache {
+    /// Create an empty cache of the given root.
+    pub fn new<T: AsRef<Path>>(root: &T) -> SourceCache {
+        SourceCache {
+            root: normalize(root),
+            entries: HashMap::new(),
+            lazy_verification: false,
+            unverified: HashSet::new(),
+        }
+    }
+
+    /// Reuse the cached content of files modified shortly before they were cached without
+    /// hashing them when loading, leaving them to be verified by `verify`. This is disabled
+    /// by default.
+    pub fn with_lazy_verification(mut self, lazy_verification: bool) -> SourceCache {
+        self.lazy_verification = lazy_verification;
+
+        self
+    }
+
+    /// Open the cache of the given root from the given file.
+    ///
+    /// If the file is missing, corrupted, or was written for another root, the cache is empty.
+    pub fn open<F: AsRef<Path>, R: AsRef<Path>>(file: &F, root: &R) -> SourceCache {
+        let cache = SourceCache::new(root);
+
+        match std::fs::read(verbatim_path(file)) {
+            Ok(bytes) => decode(&bytes)
+                .filter(|decoded| decoded.root == cache.root)
+                .unwrap_or(cache),
+            Err(_) => cache,
+        }
+    }
+
+    /// Write the cache to the given file, creating its parent directories.
+    pub fn save<T: AsRef<Path>>(&self, file: &T) -> Result<(), Error> {
+        let file = verbatim_path(file);
+
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(file, encode(self))?;
+
+        Ok(())
+    }
+
+    /// Get the root of this cache.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the number of cached sources that were reused without being hashed, and are
+    /// waiting to be verified, see `with_lazy_verification`.
+    pub fn unverified(&self) -> usize {
+        self.unverified.len()
+    }
+
+    /// Get the number of cached sources.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Load a source map from the given directory, reusing the cached content of unchanged
+    /// files, and updating the cache with the files that were read.
+    ///
+    /// Cached sources of files within the directory that no longer exist are forgotten.
+    ///
+    /// The directory is walked with the rules of the given loader, whose root must be the
+    /// root of this cache, otherwise `Error::InvalidSource` is returned.
+    ///
+    /// Definitions are placed before scripts in the returned source map.
+    pub fn load<T: AsRef<Path>>(
+        &mut self,
+        loader: &DirectorySourceLoader,
+        directory: &T,
+    ) -> Result<(SourceMap, CacheStats), Error> {
+        let directory = directory.as_ref();
+
+        if normalize(&loader.root) != self.root {
+            return Err(Error::InvalidSource(format!(
+                "the cache of `{}` cannot load sources of `{}`.",
+                self.root.to_string_lossy(),
+                loader.root.to_string_lossy()
+            )));
+        }
+
+        if !loader.supports(directory) {
+            return Err(Error::InvalidSource(format!(
+                "source `{}` is not supported.",
+                directory.to_string_lossy()
+            )));
+        }
+
+        let directory = resolve(&loader.root, directory);
+        let prefix = normalize(&directory)
+            .strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut map = SourceMap::new(vec![]);
+        let mut stats = CacheStats::default();
+        let mut seen = HashSet::new();
+
+        walk(loader, &directory, &mut |file, kind| {
+            let metadata =
+                std::fs::metadata(verbatim_path(&file)).map_err(|error| read_error(file, error))?;
+            let origin = normalize(&file)
+                .strip_prefix(&self.root)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap();
+
+            let fresh = match self.entries.get_mut(&origin) {
+                Some(entry) if entry.matches(kind, &metadata) => {
+                    if !entry.is_ambiguous() {
+                        true
+                    } else if self.lazy_verification {
+                        self.unverified.insert(origin.clone());
+
+                        true
+                    } else {
+                        entry.verify(file)
+                    }
+                }
+                _ => false,
+            };
+
+            let content = match self.entries.get(&origin) {
+                Some(entry) if fresh => {
+                    stats.reused += 1;
+
+                    entry.content.clone()
+                }
+                _ => {
+                    let checked = timestamp(SystemTime::now());
+                    let content = std::fs::read_to_string(verbatim_path(&file))
+                        .map_err(|error| read_error(file, error))?;
+                    stats.read += 1;
+
+                    self.unverified.remove(&origin);
+                    self.entries.insert(
+                        origin.clone(),
+                        CacheEntry::new(kind, &metadata, checked, &content),
+                    );
+
+                    content
+                }
+            };
+
+            seen.insert(origin);
+            map.merge(&mut loader.loader.finish(file, kind, content, Some(&metadata))?)?;
+
+            Ok(())
+        })?;
+
+        self.entries
+            .retain(|origin, _| seen.contains(origin) || !Path::new(origin).starts_with(&prefix));
+        self.unverified
+            .retain(|origin| self.entries.contains_key(origin));
+
+        map.sort_definitions_first();
+
+        Ok((map, stats))
+    }
+
+    /// Hash the files whose cached content was reused without being hashed, and replace
+    /// the content of their sources in the given source map if they changed, returning the
+    /// identifiers of the changed sources.
+    ///
+    /// Pragmas are scanned again if they were scanned before, and the cache is updated with
+    /// the content that was read. If a file cannot be read, the error is returned, and the
+    /// files that were not verified yet are verified by the next call.
+    pub fn verify(&mut self, map: &mut SourceMap) -> Result<Vec<SourceId>, Error> {
+        let mut unverified = self.unverified.iter().cloned().collect::<Vec<_>>();
+        unverified.sort();
+
+        let mut changed = vec![];
+        for origin in unverified {
+            let file = self.root.join(&origin);
+
+            if let Some(entry) = self.entries.get_mut(&origin) {
+                let checked = timestamp(SystemTime::now());
+                let metadata = std::fs::metadata(verbatim_path(&file))
+                    .map_err(|error| read_error(&file, error))?;
+                let content = std::fs::read_to_string(verbatim_path(&file))
+                    .map_err(|error| read_error(&file, error))?;
+
+                if digest(content.as_bytes()) == entry.digest {
+                    entry.checked = checked;
+                } else {
+                    *entry = CacheEntry::new(entry.kind, &metadata, checked, &content);
+
+                    if let Some(id) = map.id_of(&origin) {
+                        let source = &mut map[id];
+                        if source.content != content {
+                            source.content = content;
+                            if source.pragmas.is_some() {
+                                source.scan_pragmas();
+                            }
+
+                            changed.push(id);
+                        }
+                    }
+                }
+            }
+
+            self.unverified.remove(&origin);
+        }
+
+        Ok(changed)
+    }
+}
+
+impl CacheEntry {
+    fn new(
+        kind: SourceKind,
+        metadata: &Metadata,
+        checked: Option<(u64, u32)>,
+        content: &str,
+    ) -> CacheEntry {
+        CacheEntry {
+            kind,
+            size: metadata.len(),
+            modified: metadata.modified().ok().and_then(timestamp),
+            checked,
+            digest: digest(content.as_bytes()),
+            content: content.to_string(),
+        }
+    }
+
+    /// Check if the kind, size and modification time of the cached content still match the
+    /// given file.
+    fn matches(&self, kind: SourceKind, metadata: &Metadata) -> bool {
+        self.modified.is_some()
+            && self.kind == kind
+            && self.size == metadata.len()
+            && self.modified == metadata.modified().ok().and_then(timestamp)
+    }
+
+    /// Check if the file was modified within `MODIFIED_GRANULARITY` of when it was last
+    /// checked, so it may have changed without changing its modification time.
+    fn is_ambiguous(&self) -> bool {
+        match (self.modified, self.checked) {
+            (Some(modified), Some(checked)) => {
+                let since = Duration::new(checked.0, checked.1)
+                    .saturating_sub(Duration::new(modified.0, modified.1));
+
+                since < MODIFIED_GRANULARITY
+            }
+            _ => true,
+        }
+    }
+
+    /// Hash the given file, and when its content is still the cached content, record it as
+    /// checked now.
+    fn verify(&mut self, file: &Path) -> bool {
+        let checked = timestamp(SystemTime::now());
+        match std::fs::read(verbatim_path(&file)) {
+            Ok(bytes) if digest(&bytes) == self.digest => {
+                self.checked = checked;
+
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn timestamp(time: SystemTime) -> Option<(u64, u32)> {
+    let time = time.duration_since(UNIX_EPOCH).ok()?;
+
+    Some((time.as_secs(), time.subsec_nanos()))
+}
+
+fn digest(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn encode(cache: &SourceCache) -> Vec<u8> {
+    let mut bytes = CACHE_MAGIC.to_vec();
+
+    write_string(&mut bytes, &cache.root.to_string_lossy());
+    write_u64(&mut bytes, cache.entries.len() as u64);
+
+    for (origin, entry) in &cache.entries {
+        write_string(&mut bytes, origin);
+        bytes.push(match entry.kind {
+            SourceKind::Definition => 0,
+            SourceKind::Script => 1,
+        });
+        write_u64(&mut bytes, entry.size);
+        write_timestamp(&mut bytes, entry.modified);
+        write_timestamp(&mut bytes, entry.checked);
+        write_u64(&mut bytes, entry.digest);
+        write_string(&mut bytes, &entry.content);
+    }
+
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<SourceCache> {
+    let mut reader = Reader(bytes.strip_prefix(CACHE_MAGIC)?);
+
+    let mut cache = SourceCache::new(&reader.string()?);
+
+    for _ in 0..reader.u64()? {
+        let origin = reader.string()?;
+        let kind = match reader.byte()? {
+            0 => SourceKind::Definition,
+            1 => SourceKind::Script,
+            _ => return None,
+        };
+        let size = reader.u64()?;
+        let modified = reader.timestamp()?;
+        let checked = reader.timestamp()?;
+        let digest = reader.u64()?;
+        let content = reader.string()?;
+
+        cache.entries.insert(
+            origin,
+            CacheEntry {
+                kind,
+                size,
+                modified,
+                checked,
+                digest,
+                content,
+            },
+        );
+    }
+
+    reader.0.is_empty().then_some(cache)
+}
+
+fn write_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_timestamp(bytes: &mut Vec<u8>, value: Option<(u64, u32)>) {
+    match value {
+        Some((seconds, nanoseconds)) => {
+            bytes.push(1);
+            write_u64(bytes, seconds);
+            write_u64(bytes, nanoseconds as u64);
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, value: &str) {
+    write_u64(bytes, value.len() as u64);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, length: usize) -> Option<&[u8]> {
+        if self.0.len() < length {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+
+        Some(taken)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn timestamp(&mut self) -> Option<Option<(u64, u32)>> {
+        match self.byte()? {
+            0 => Some(None),
+            1 => Some(Some((self.u64()?, u32::try_from(self.u64()?).ok()?))),
+            _ => None,
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let length = usize::try_from(self.u64()?).ok()?;
+
+        String::from_utf8(self.take(length)?.to_vec()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    #[test]
+    fn test_source_cache() {
+        let root = TempDir::new("cache");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.ara"), "function main(): void {}").unwrap();
+        std::fs::write(root.join("src/foo.d.ara"), "function foo(): void;").unwrap();
+
+        let file = cache_file(&root.join(".cache"), &root);
+        let loader = DirectorySourceLoader::new(&root);
+
+        let mut cache = SourceCache::open(&file, &root);
+        assert!(cache.is_empty());
+
+        let (map, stats) = cache.load(&loader, &"src").unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.sources()[0].kind, SourceKind::Definition);
+        assert_eq!(stats, CacheStats { reused: 0, read: 2 });
+        cache.save(&file).unwrap();
+
+        let mut cache = SourceCache::open(&file, &root);
+        assert_eq!(cache.len(), 2);
+
+        let (cached, stats) = cache.load(&loader, &"src").unwrap();
+        assert_eq!(cached.sources(), map.sources());
+        assert_eq!(stats, CacheStats { reused: 2, read: 0 });
+
+        // a same-size change keeping the modification time of a recently cached file is hashed.
+        let main = root.join("src/main.ara");
+        let modified = std::fs::metadata(&main).unwrap().modified().unwrap();
+        std::fs::write(&main, "function main(): var {}").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&main)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let (changed, stats) = cache.load(&loader, &"src").unwrap();
+        assert_eq!(changed.sources()[1].content, "function main(): var {}");
+        assert_eq!(stats, CacheStats { reused: 1, read: 1 });
+
+        std::fs::write(root.join("src/main.ara"), "function main(): int {}").unwrap();
+        std::fs::remove_file(root.join("src/foo.d.ara")).unwrap();
+
+        let (map, stats) = cache.load(&loader, &"src").unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.sources()[0].content, "function main(): int {}");
+        assert_eq!(stats, CacheStats { reused: 0, read: 1 });
+        assert_eq!(cache.len(), 1);
+
+        assert!(SourceCache::open(&file, &root.join("src")).is_empty());
+        assert!(SourceCache::new(&root.join("src"))
+            .load(&loader, &"src")
+            .is_err());
+
+        std::fs::write(&file, &CACHE_MAGIC[..4]).unwrap();
+        assert!(SourceCache::open(&file, &root).is_empty());
+    }
+
+    #[test]
+    fn test_lazy_verification() {
+        let root = TempDir::new("cache_lazy_verification");
+        let main = root.join("main.ara");
+        std::fs::write(&main, "// ara-language-version: 1\n").unwrap();
+
+        let file = cache_file(&root.join(".cache"), &root);
+        let loader = DirectorySourceLoader::new(&root).with_pragmas(true);
+
+        let mut cache = SourceCache::new(&root).with_lazy_verification(true);
+        cache.load(&loader, &".").unwrap();
+        cache.save(&file).unwrap();
+
+        // a same-size change keeping the modification time is reused until it is verified.
+        let modified = std::fs::metadata(&main).unwrap().modified().unwrap();
+        std::fs::write(&main, "// ara-language-version: 2\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&main)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let mut cache = SourceCache::open(&file, &root).with_lazy_verification(true);
+        let (mut map, stats) = cache.load(&loader, &".").unwrap();
+        assert_eq!(stats, CacheStats { reused: 1, read: 0 });
+        assert_eq!(cache.unverified(), 1);
+        assert_eq!(map.sources()[0].content, "// ara-language-version: 1\n");
+
+        let changed = cache.verify(&mut map).unwrap();
+        assert_eq!(changed, map.ids().collect::<Vec<_>>());
+        assert_eq!(cache.unverified(), 0);
+        assert_eq!(map.sources()[0].content, "// ara-language-version: 2\n");
+        assert_eq!(
+            map.sources()[0].pragmas.as_ref().unwrap().language_version,
+            Some("2".to_string())
+        );
+
+        // the cache was updated, so the next load reuses the new content.
+        let (mut map, _) = cache.load(&loader, &".").unwrap();
+        assert_eq!(map.sources()[0].content, "// ara-language-version: 2\n");
+        assert!(cache.verify(&mut map).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::error::Error;
+use crate::source::Source;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// A composition of several independent source maps ( workspaces ).
+///
+/// Each workspace is identified by a name, which qualifies the identifiers and
+/// origins of its sources, so identical origins in two workspaces do not collide.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::unstable::federation::FederatedSourceMap;
+/// use ara_source::source::Source;
+/// use ara_source::source::SourceKind;
+/// use ara_source::SourceMap;
+///
+/// let mut federation = FederatedSourceMap::new();
+///
+/// let app = SourceMap::single(Source::new(SourceKind::Script, "src/main.ara", ""));
+/// let mut lib = SourceMap::new(vec![]);
+/// let id = lib.add(Source::new(SourceKind::Script, "src/main.ara", "")).unwrap();
+///
+/// federation.add("app", app).unwrap();
+/// federation.add("lib", lib).unwrap();
+///
+/// assert_eq!(federation.get("lib", id).unwrap().name(), "src/main.ara");
+/// assert_eq!(federation.find("src/main.ara").unwrap().0, "app");
+/// assert_eq!(federation.iter().count(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct FederatedSourceMap {
+    workspaces: Vec<(String, SourceMap)>,
+}
+
+impl FederatedSourceMap {
+    pub fn new() -> FederatedSourceMap {
+        FederatedSourceMap::default()
+    }
+
+    /// Add a workspace with the given name.
+    ///
+    /// If a workspace with the same name exists, `Error::InvalidSource` is returned.
+    pub fn add<T: Into<String>>(&mut self, name: T, map: SourceMap) -> Result<(), Error> {
+        let name = name.into();
+
+        if self.workspace(&name).is_ok() {
+            return Err(Error::InvalidSource(format!(
+                "workspace `{name}` already exists."
+            )));
+        }
+
+        self.workspaces.push((name, map));
+
+        Ok(())
+    }
+
+    /// Get a workspace by its name.
+    ///
+    /// If the workspace is not found, `Error::SourceNotFound` is returned.
+    pub fn workspace<T: AsRef<str>>(&self, name: T) -> Result<&SourceMap, Error> {
+        let name = name.as_ref();
+
+        self.workspaces
+            .iter()
+            .find(|(workspace, _)| workspace == name)
+            .map(|(_, map)| map)
+            .ok_or_else(|| Error::SourceNotFound(name.to_string()))
+    }
+
+    /// Get a source by its workspace and identifier.
+    ///
+    /// If the workspace or the source is not found, `Error::SourceNotFound` is returned.
+    pub fn get<T: AsRef<str>>(&self, workspace: T, id: SourceId) -> Result<&Source, Error> {
+        self.workspace(workspace)?
+            .source(id)
+            .ok_or_else(|| Error::SourceNotFound(id.to_string()))
+    }
+
+    /// Find a source by its workspace and origin.
+    pub fn named<W: AsRef<str>, T: AsRef<str>>(
+        &self,
+        workspace: W,
+        name: T,
+    ) -> Result<&Source, Error> {
+        self.workspace(workspace)?.named(name)
+    }
+
+    /// Find a source by its origin, in all workspaces, in the order they were added.
+    ///
+    /// The name of the workspace containing the source is returned along with it.
+    pub fn find<T: AsRef<str>>(&self, name: T) -> Result<(&str, &Source), Error> {
+        let name = name.as_ref();
+
+        self.workspaces
+            .iter()
+            .find_map(|(workspace, map)| {
+                map.named(name)
+                    .ok()
+                    .map(|source| (workspace.as_str(), source))
+            })
+            .ok_or_else(|| Error::SourceNotFound(name.to_string()))
+    }
+
+    /// Iterate over all sources of all workspaces, along with their workspace name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Source)> {
+        self.workspaces
+            .iter()
+            .flat_map(|(workspace, map)| map.iter().map(move |source| (workspace.as_str(), source)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::SourceKind;
+
+    #[test]
+    fn test_federated_source_map() {
+        let mut federation = FederatedSourceMap::new();
+
+        federation
+            .add(
+                "app",
+                SourceMap::new(vec![
+                    Source::new(SourceKind::Script, "src/main.ara", "app"),
+                    Source::new(SourceKind::Script, "src/app.ara", "app"),
+                ]),
+            )
+            .unwrap();
+        federation
+            .add(
+                "lib",
+                SourceMap::new(vec![
+                    Source::new(SourceKind::Script, "src/main.ara", "lib"),
+                    Source::new(SourceKind::Definition, "src/lib.d.ara", "lib"),
+                ]),
+            )
+            .unwrap();
+
+        assert!(federation.add("lib", SourceMap::new(vec![])).is_err());
+
+        let first = SourceId(0);
+
+        assert_eq!(federation.get("app", first).unwrap().content, "app");
+        assert_eq!(federation.get("lib", first).unwrap().content, "lib");
+        assert!(federation.get("lib", SourceId(2)).is_err());
+        assert!(federation.get("missing", first).is_err());
+
+        assert_eq!(
+            federation.named("lib", "src/main.ara").unwrap().content,
+            "lib"
+        );
+
+        let (workspace, source) = federation.find("src/lib.d.ara").unwrap();
+        assert_eq!(workspace, "lib");
+        assert_eq!(source.kind, SourceKind::Definition);
+        assert!(federation.find("src/missing.ara").is_err());
+
+        assert_eq!(
+            federation
+                .iter()
+                .map(|(workspace, source)| format!("{workspace}:{}", source.name()))
+                .collect::<Vec<String>>(),
+            vec![
+                "app:src/main.ara",
+                "app:src/app.ara",
+                "lib:src/main.ara",
+                "lib:src/lib.d.ara",
+            ]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::loader::load_directories;
+use crate::source::Source;
+use crate::source::SourceKind;
+use crate::SourceId;
+use crate::SourceMap;
+
+/// The kind returned by `SourceMapHandles::kind` for scripts.
+pub const SCRIPT_KIND: &str = "script";
+
+/// The kind returned by `SourceMapHandles::kind` for definitions.
+pub const DEFINITION_KIND: &str = "definition";
+
+/// A store of source maps addressed by integer handles, for binding generators ( e.g PyO3, napi ).
+///
+/// Source maps are identified by the handle returned when they are inserted, and sources
+/// by the index of their `SourceId`, so no lifetimes cross the binding boundary, and every
+/// getter returns an owned copy.
+///
+/// Handles are never reused, a handle of a removed source map stays invalid.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::unstable::handle::SourceMapHandles;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+///
+/// let mut handles = SourceMapHandles::new();
+/// let map = handles.load_directories(&root, vec!["src".to_string()]).unwrap();
+///
+/// let source = handles.named(map, "src/main.ara").unwrap();
+/// assert_eq!(handles.kind(map, source).unwrap(), "script");
+/// assert_eq!(handles.sources(map).unwrap(), vec![source]);
+///
+/// assert!(handles.free(map));
+/// assert!(handles.len(map).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct SourceMapHandles {
+    maps: HashMap<u64, SourceMap>,
+    next: u64,
+}
+
+impl SourceMapHandles {
+    pub fn new() -> SourceMapHandles {
+        SourceMapHandles::default()
+    }
+
+    /// Insert the given source map, returning its handle.
+    pub fn insert(&mut self, map: SourceMap) -> u64 {
+        let handle = self.next;
+
+        self.next += 1;
+        self.maps.insert(handle, map);
+
+        handle
+    }
+
+    /// Load a source map from the given directories of the given root, see `load_directories`.
+    pub fn load_directories(&mut self, root: &str, directories: Vec<String>) -> Result<u64, Error> {
+        Ok(self.insert(load_directories(root, directories)?))
+    }
+
+    /// Remove the source map of the given handle, returning it.
+    pub fn remove(&mut self, map: u64) -> Option<SourceMap> {
+        self.maps.remove(&map)
+    }
+
+    /// Free the source map of the given handle, returning `false` if it was not found.
+    pub fn free(&mut self, map: u64) -> bool {
+        self.remove(map).is_some()
+    }
+
+    /// Get the source map of the given handle.
+    ///
+    /// If the handle is not valid, `Error::SourceNotFound` is returned.
+    pub fn get(&self, map: u64) -> Result<&SourceMap, Error> {
+        self.maps
+            .get(&map)
+            .ok_or_else(|| Error::SourceNotFound(format!("source map {map}")))
+    }
+
+    /// Get the number of sources in the source map of the given handle.
+    pub fn len(&self, map: u64) -> Result<usize, Error> {
+        Ok(self.get(map)?.len())
+    }
+
+    /// Get the sources of the source map of the given handle, in order.
+    pub fn sources(&self, map: u64) -> Result<Vec<u64>, Error> {
+        Ok(self.get(map)?.ids().map(|id| id.index() as u64).collect())
+    }
+
+    /// Get the source with the given origin in the source map of the given handle.
+    pub fn named(&self, map: u64, origin: &str) -> Result<u64, Error> {
+        self.get(map)?
+            .id_of(origin)
+            .map(|id| id.index() as u64)
+            .ok_or_else(|| Error::SourceNotFound(origin.to_string()))
+    }
+
+    /// Add a source to the source map of the given handle, returning the added source.
+    ///
+    /// The kind must be either `SCRIPT_KIND` or `DEFINITION_KIND`, otherwise
+    /// `Error::InvalidSource` is returned.
+    pub fn add(&mut self, map: u64, kind: &str, origin: &str, content: &str) -> Result<u64, Error> {
+        let kind = match kind {
+            SCRIPT_KIND => SourceKind::Script,
+            DEFINITION_KIND => SourceKind::Definition,
+            _ => {
+                return Err(Error::InvalidSource(format!(
+                    "source kind `{kind}` is not supported."
+                )))
+            }
+        };
+
+        let map = self
+            .maps
+            .get_mut(&map)
+            .ok_or_else(|| Error::SourceNotFound(format!("source map {map}")))?;
+
+        Ok(map.add(Source::new(kind, origin, content))?.index() as u64)
+    }
+
+    /// Get the kind of the given source, either `SCRIPT_KIND` or `DEFINITION_KIND`.
+    pub fn kind(&self, map: u64, source: u64) -> Result<&'static str, Error> {
+        Ok(match self.source(map, source)?.kind {
+            SourceKind::Script => SCRIPT_KIND,
+            SourceKind::Definition => DEFINITION_KIND,
+        })
+    }
+
+    /// Get a copy of the origin of the given source, if any.
+    pub fn origin(&self, map: u64, source: u64) -> Result<Option<String>, Error> {
+        Ok(self.source(map, source)?.origin.clone())
+    }
+
+    /// Get a copy of the name of the given source, see `Source::name`.
+    pub fn name(&self, map: u64, source: u64) -> Result<String, Error> {
+        Ok(self.source(map, source)?.name().to_string())
+    }
+
+    /// Get a copy of the content of the given source.
+    pub fn content(&self, map: u64, source: u64) -> Result<String, Error> {
+        Ok(self.source(map, source)?.content.clone())
+    }
+
+    /// Get the fingerprint of the given source, see `Source::fingerprint`.
+    pub fn fingerprint(&self, map: u64, source: u64) -> Result<u64, Error> {
+        Ok(self.source(map, source)?.fingerprint())
+    }
+
+    fn source(&self, map: u64, source: u64) -> Result<&Source, Error> {
+        let map = self.get(map)?;
+
+        usize::try_from(source)
+            .ok()
+            .and_then(|index| map.source(SourceId(index)))
+            .ok_or_else(|| Error::SourceNotFound(format!("source #{source}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map_handles() {
+        let mut handles = SourceMapHandles::new();
+
+        let first = handles.insert(SourceMap::new(vec![]));
+        let second = handles.insert(SourceMap::new(vec![]));
+        assert_ne!(first, second);
+
+        let foo = handles.add(first, SCRIPT_KIND, "foo.ara", "foo").unwrap();
+        let bar = handles
+            .add(first, DEFINITION_KIND, "bar.d.ara", "")
+            .unwrap();
+
+        assert_eq!(handles.len(first).unwrap(), 2);
+        assert_eq!(handles.len(second).unwrap(), 0);
+        assert_eq!(handles.sources(first).unwrap(), vec![foo, bar]);
+        assert_eq!(handles.named(first, "bar.d.ara").unwrap(), bar);
+        assert_eq!(handles.kind(first, bar).unwrap(), DEFINITION_KIND);
+        assert_eq!(handles.origin(first, foo).unwrap().unwrap(), "foo.ara");
+        assert_eq!(handles.name(first, foo).unwrap(), "foo.ara");
+        assert_eq!(handles.content(first, foo).unwrap(), "foo");
+        assert_eq!(
+            handles.fingerprint(first, foo).unwrap(),
+            handles.get(first).unwrap().sources()[0].fingerprint()
+        );
+
+        assert!(handles.add(first, "module", "baz.ara", "").is_err());
+        assert!(handles.add(first, SCRIPT_KIND, "foo.ara", "").is_err());
+        assert!(handles.content(second, foo).is_err());
+        assert!(handles.content(first, u64::MAX).is_err());
+        assert!(handles.named(first, "baz.ara").is_err());
+
+        assert!(handles.free(first));
+        assert!(!handles.free(first));
+        assert!(handles.kind(first, foo).is_err());
+        assert_ne!(handles.insert(SourceMap::new(vec![])), first);
+    }
+}
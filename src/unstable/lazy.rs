@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::loader::SourceLoader;
+use crate::source::Source;
+use crate::MergeStrategy;
+use crate::SourceMap;
+
+/// A source map that is materialized by a loader on first lookup.
+///
+/// Sources are loaded when they are first looked up by origin, and directories ( e.g. the
+/// directory of a namespace ) when they are first looked up, so sources that are never
+/// referenced are never read.
+///
+/// Example:
+///
+/// ```rust
+/// use ara_source::unstable::lazy::LazySourceMap;
+/// use ara_source::loader::CompositeSourceLoader;
+/// use ara_source::loader::DirectorySourceLoader;
+/// use ara_source::loader::FileSourceLoader;
+///
+/// let root = format!("{}/examples/fixture/", env!("CARGO_MANIFEST_DIR"));
+///
+/// let mut map = LazySourceMap::new(CompositeSourceLoader::new(vec![
+///     Box::new(FileSourceLoader::new(&root)),
+///     Box::new(DirectorySourceLoader::new(&root)),
+/// ]));
+///
+/// assert!(map.map().is_empty());
+///
+/// assert_eq!(map.named("src/main.ara").unwrap().name(), "src/main.ara");
+/// assert_eq!(map.map().len(), 1);
+///
+/// assert_eq!(map.directory("vendor/foo").unwrap().len(), 1);
+/// assert_eq!(map.map().len(), 2);
+/// ```
+#[derive(Debug)]
+pub struct LazySourceMap<L: SourceLoader> {
+    loader: L,
+    map: SourceMap,
+    // the directories that were already loaded, along with their subdirectories.
+    directories: HashSet<String>,
+}
+
+impl<L: SourceLoader> LazySourceMap<L> {
+    pub fn new(loader: L) -> LazySourceMap<L> {
+        LazySourceMap {
+            loader,
+            map: SourceMap::new(vec![]),
+            directories: HashSet::new(),
+        }
+    }
+
+    /// Get the loader of this source map.
+    pub fn loader(&self) -> &L {
+        &self.loader
+    }
+
+    /// Get the sources materialized so far.
+    pub fn map(&self) -> &SourceMap {
+        &self.map
+    }
+
+    /// Consume this source map, returning the sources materialized so far.
+    pub fn into_map(self) -> SourceMap {
+        self.map
+    }
+
+    /// Get the source with the given origin, loading it if it was not materialized yet.
+    ///
+    /// If the loader fails, or does not produce a source with the given origin, an error
+    /// is returned, and the lookup is retried on the next call.
+    pub fn named(&mut self, origin: &str) -> Result<&Source, Error> {
+        let id = self.map.entry(origin).or_load_with(&self.loader)?;
+
+        Ok(&self.map[id])
+    }
+
+    /// Get the sources within the given directory, loading it if neither it nor one of its
+    /// parent directories was loaded yet.
+    ///
+    /// Sources of the directory that were already materialized are kept.
+    ///
+    /// If the loader fails, an error is returned, and the directory is loaded again on the next call.
+    pub fn directory(&mut self, directory: &str) -> Result<Vec<&Source>, Error> {
+        let loaded = self
+            .directories
+            .iter()
+            .any(|loaded| Path::new(directory).starts_with(loaded));
+
+        if !loaded {
+            let mut sources = self.loader.load(Path::new(directory))?;
+
+            self.map
+                .merge_with(&mut sources, MergeStrategy::PreferExisting)?;
+            self.directories.insert(directory.to_string());
+        }
+
+        Ok(self
+            .map
+            .iter()
+            .filter(|source| {
+                source
+                    .origin
+                    .as_ref()
+                    .is_some_and(|origin| Path::new(origin).starts_with(directory))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::DirectorySourceLoader;
+    use crate::loader::FileSourceLoader;
+
+    #[test]
+    fn test_lazy_source_map() {
+        let root = format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        );
+
+        let mut map = LazySourceMap::new(FileSourceLoader::new(&root));
+        assert!(map.named("src/missing.ara").is_err());
+        assert!(map.directory("vendor").is_err());
+
+        let source = map.named("vendor/bar/bar.d.ara").unwrap().clone();
+        assert_eq!(map.named("vendor/bar/bar.d.ara").unwrap(), &source);
+        assert_eq!(map.map().len(), 1);
+
+        let mut map = LazySourceMap::new(DirectorySourceLoader::new(&root));
+        assert_eq!(map.directory("vendor").unwrap().len(), 2);
+        assert_eq!(map.directory("vendor/bar").unwrap().len(), 1);
+        assert_eq!(map.into_map().len(), 2);
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use notify::event::ModifyKind;
+use notify::event::RenameMode;
+use notify::EventKind;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Watcher;
+
+use crate::error::Error;
+use crate::loader::walk;
+use crate::loader::DirectorySourceLoader;
+use crate::loader::InclusionDecision;
+use crate::path::relative_to;
+
+/// A change to a source within a watched root, keyed by origin.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SourceEvent {
+    /// A source was created, or moved into the root.
+    Added(String),
+
+    /// The content of a source was modified.
+    Modified(String),
+
+    /// A source was removed, or moved out of the root.
+    Removed(String),
+}
+
+/// A watcher of the root of a `DirectorySourceLoader`, emitting a `SourceEvent` for each
+/// change to a file the loader would load.
+///
+/// Directories created in, moved into, or moved out of the root emit an event for each
+/// source within them. Events are sent until the watcher is dropped.
+///
+/// Example:
+///
+/// ```rust,no_run
+/// use ara_source::loader::DirectorySourceLoader;
+/// use ara_source::unstable::watch::SourceEvent;
+/// use ara_source::unstable::watch::SourceWatcher;
+///
+/// let loader = DirectorySourceLoader::new(&"/path/to/project");
+/// let (_watcher, events) = SourceWatcher::new(loader).unwrap();
+///
+/// for event in events {
+///     match event {
+///         SourceEvent::Added(origin) => println!("added {origin}"),
+///         SourceEvent::Modified(origin) => println!("modified {origin}"),
+///         SourceEvent::Removed(origin) => println!("removed {origin}"),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SourceWatcher {
+    loader: Arc<DirectorySourceLoader>,
+
+    // events are sent for as long as the underlying watcher is alive.
+    _watcher: RecommendedWatcher,
+}
+
+impl SourceWatcher {
+    /// Start watching the root of the given loader, returning the watcher and its events.
+    ///
+    /// The root is walked once, to know which sources a removed directory contained.
+    pub fn new(
+        loader: DirectorySourceLoader,
+    ) -> Result<(SourceWatcher, Receiver<SourceEvent>), Error> {
+        let loader = Arc::new(loader);
+        let (sender, receiver) = channel();
+
+        let mut known = BTreeSet::new();
+        walk(&loader, &loader.root, &mut |file, _| {
+            known.extend(origin(&loader, file));
+
+            Ok(())
+        })?;
+
+        let handler = Arc::clone(&loader);
+        let known = Mutex::new(known);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    dispatch(&handler, &known, &sender, event);
+                }
+            })
+            .map_err(std::io::Error::other)?;
+
+        watcher
+            .watch(&loader.root, RecursiveMode::Recursive)
+            .map_err(std::io::Error::other)?;
+
+        Ok((
+            SourceWatcher {
+                loader,
+                _watcher: watcher,
+            },
+            receiver,
+        ))
+    }
+
+    /// Get the root watched by this watcher.
+    pub fn root(&self) -> &Path {
+        &self.loader.root
+    }
+}
+
+fn dispatch(
+    loader: &DirectorySourceLoader,
+    known: &Mutex<BTreeSet<String>>,
+    sender: &Sender<SourceEvent>,
+    event: notify::Event,
+) {
+    let mut known = known.lock().unwrap_or_else(PoisonError::into_inner);
+    let paths = event.paths.iter();
+
+    let events: Vec<SourceEvent> = match event.kind {
+        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => paths
+            .flat_map(|path| added(loader, &mut known, path))
+            .map(SourceEvent::Added)
+            .collect(),
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => paths
+            .flat_map(|path| removed(loader, &mut known, path))
+            .map(SourceEvent::Removed)
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let removed = removed(loader, &mut known, &event.paths[0]);
+            let added = added(loader, &mut known, &event.paths[1]);
+
+            removed
+                .into_iter()
+                .map(SourceEvent::Removed)
+                .chain(added.into_iter().map(SourceEvent::Added))
+                .collect()
+        }
+        EventKind::Modify(ModifyKind::Name(_)) => paths
+            .flat_map(|path| {
+                let added = added(loader, &mut known, path);
+                if added.is_empty() {
+                    removed(loader, &mut known, path)
+                        .into_iter()
+                        .map(SourceEvent::Removed)
+                        .collect()
+                } else {
+                    added
+                        .into_iter()
+                        .map(SourceEvent::Added)
+                        .collect::<Vec<_>>()
+                }
+            })
+            .collect(),
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any | ModifyKind::Other) => paths
+            .filter_map(|path| match loader.explain(&path) {
+                InclusionDecision::Included(_) => origin(loader, path),
+                _ => None,
+            })
+            .inspect(|origin| {
+                known.insert(origin.clone());
+            })
+            .map(SourceEvent::Modified)
+            .collect(),
+        _ => vec![],
+    };
+
+    for event in events {
+        // the receiver was dropped, nobody is listening anymore.
+        if sender.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Get the origins of the new sources at the given path, a source, or a directory of sources.
+fn added(loader: &DirectorySourceLoader, known: &mut BTreeSet<String>, path: &Path) -> Vec<String> {
+    let mut origins = vec![];
+
+    match loader.explain(&path) {
+        InclusionDecision::Included(_) => origins.extend(origin(loader, path)),
+        InclusionDecision::Traversed => {
+            // the directory may be removed while it is walked, its removal is reported then.
+            let _ = walk(loader, path, &mut |file, _| {
+                origins.extend(origin(loader, file));
+
+                Ok(())
+            });
+        }
+        _ => {}
+    }
+
+    // a directory and the files within it may be reported separately.
+    origins.retain(|origin| known.insert(origin.clone()));
+
+    origins
+}
+
+/// Get the origins of the known sources removed with the given path, a source, or a directory.
+fn removed(
+    loader: &DirectorySourceLoader,
+    known: &mut BTreeSet<String>,
+    path: &Path,
+) -> Vec<String> {
+    let prefix = match loader.explain(&path) {
+        InclusionDecision::NotFound => match origin(loader, path) {
+            Some(prefix) => prefix,
+            None => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    let origins = known
+        .range(prefix.clone()..)
+        .take_while(|origin| origin.starts_with(&prefix))
+        .filter(|origin| {
+            prefix.is_empty()
+                || origin.len() == prefix.len()
+                || origin[prefix.len()..].starts_with('/')
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for origin in &origins {
+        known.remove(origin);
+    }
+
+    origins
+}
+
+fn origin(loader: &DirectorySourceLoader, path: &Path) -> Option<String> {
+    relative_to(&loader.root, &path).map(|origin| origin.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::time::Duration;
+
+    use super::*;
+
+    use crate::temp_dir::TempDir;
+
+    /// Wait for the given events, in any order, failing on any event about a `.txt` file.
+    fn expect(events: &Receiver<SourceEvent>, expected: &[SourceEvent]) {
+        let mut expected = expected.to_vec();
+
+        while !expected.is_empty() {
+            let event = events
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap_or_else(|_| panic!("expected {expected:?}."));
+
+            assert!(
+                !matches!(&event, SourceEvent::Added(origin) | SourceEvent::Modified(origin) if origin.ends_with(".txt"))
+            );
+
+            expected.retain(|expected| expected != &event);
+        }
+    }
+
+    #[test]
+    fn test_source_watcher() {
+        let root = TempDir::new("source_watcher");
+        let outside = TempDir::new("source_watcher_outside");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(outside.join("lib")).unwrap();
+        std::fs::write(root.join("src/existing.ara"), "").unwrap();
+        std::fs::write(outside.join("lib/foo.ara"), "").unwrap();
+        std::fs::write(outside.join("lib/bar.d.ara"), "").unwrap();
+
+        let (watcher, events) = SourceWatcher::new(DirectorySourceLoader::new(&root)).unwrap();
+        assert_eq!(watcher.root(), &*root);
+
+        std::fs::write(root.join("src/notes.txt"), "").unwrap();
+        std::fs::write(root.join("src/main.ara"), "").unwrap();
+        expect(&events, &[SourceEvent::Added("src/main.ara".to_string())]);
+
+        std::fs::write(root.join("src/main.ara"), "function main(): void {}").unwrap();
+        expect(
+            &events,
+            &[SourceEvent::Modified("src/main.ara".to_string())],
+        );
+
+        std::fs::remove_file(root.join("src/main.ara")).unwrap();
+        expect(&events, &[SourceEvent::Removed("src/main.ara".to_string())]);
+
+        std::fs::rename(outside.join("lib"), root.join("lib")).unwrap();
+        expect(
+            &events,
+            &[
+                SourceEvent::Added("lib/foo.ara".to_string()),
+                SourceEvent::Added("lib/bar.d.ara".to_string()),
+            ],
+        );
+
+        std::fs::rename(root.join("lib"), outside.join("lib")).unwrap();
+        expect(
+            &events,
+            &[
+                SourceEvent::Removed("lib/foo.ara".to_string()),
+                SourceEvent::Removed("lib/bar.d.ara".to_string()),
+            ],
+        );
+
+        std::fs::remove_dir_all(root.join("src")).unwrap();
+        expect(
+            &events,
+            &[SourceEvent::Removed("src/existing.ara".to_string())],
+        );
+
+        // once the watcher is dropped, the events are disconnected.
+        drop(watcher);
+        loop {
+            match events.recv_timeout(Duration::from_secs(5)) {
+                Ok(_) => {}
+                Err(error) => {
+                    assert_eq!(error, RecvTimeoutError::Disconnected);
+                    break;
+                }
+            }
+        }
+
+        std::fs::remove_dir_all(&outside).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+/// An entry produced by a directory walker, see `SourceMap::from_walk`.
+///
+/// This is implemented for `PathBuf`, and for the entries of the `walkdir` and `ignore`
+/// crates when the corresponding features are enabled.
+pub trait WalkEntry {
+    /// Get the path of the entry.
+    fn path(&self) -> &Path;
+
+    /// Check if the entry is a file, only files are loaded.
+    fn is_file(&self) -> bool;
+}
+
+impl WalkEntry for PathBuf {
+    fn path(&self) -> &Path {
+        self
+    }
+
+    fn is_file(&self) -> bool {
+        Path::is_file(self)
+    }
+}
+
+#[cfg(feature = "walkdir")]
+impl WalkEntry for walkdir::DirEntry {
+    fn path(&self) -> &Path {
+        walkdir::DirEntry::path(self)
+    }
+
+    fn is_file(&self) -> bool {
+        self.file_type().is_file()
+    }
+}
+
+#[cfg(feature = "ignore")]
+impl WalkEntry for ::ignore::DirEntry {
+    fn path(&self) -> &Path {
+        ::ignore::DirEntry::path(self)
+    }
+
+    fn is_file(&self) -> bool {
+        self.file_type()
+            .is_some_and(|file_type| file_type.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::SourceMap;
+
+    fn root() -> PathBuf {
+        PathBuf::from(format!(
+            "{}/examples/fixture/",
+            std::env::var("CARGO_MANIFEST_DIR").unwrap()
+        ))
+    }
+
+    #[test]
+    fn test_from_walk() {
+        let root = root();
+        let entries = vec![
+            root.join("src"),
+            root.join("src/main.ara"),
+            root.join("../simple.rs"),
+            root.join("vendor/bar/bar.d.ara"),
+        ];
+
+        let map = SourceMap::from_walk(&root, entries).unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.sources()[0].name(), "vendor/bar/bar.d.ara");
+        assert_eq!(map.sources()[1].name(), "src/main.ara");
+    }
+
+    #[cfg(feature = "walkdir")]
+    #[test]
+    fn test_from_walkdir() {
+        let root = root();
+        let entries = walkdir::WalkDir::new(&root)
+            .into_iter()
+            .filter_map(Result::ok);
+
+        assert_eq!(SourceMap::from_walk(&root, entries).unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "ignore")]
+    #[test]
+    fn test_from_ignore_walk() {
+        let root = root();
+        let entries = ::ignore::WalkBuilder::new(&root)
+            .build()
+            .filter_map(Result::ok);
+
+        assert_eq!(SourceMap::from_walk(&root, entries).unwrap().len(), 3);
+    }
+}
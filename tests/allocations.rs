@@ -0,0 +1,77 @@
+//! Allocation tests, kept in their own test binary so that the counting allocator does not
+//! replace the global allocator of the other tests.
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::cell::Cell;
+
+use ara_source::source::Source;
+use ara_source::source::SourceKind;
+use ara_source::SourceMap;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|allocations| allocations.get())
+}
+
+#[test]
+fn test_named_does_not_allocate() {
+    let mut map = SourceMap::new(vec![
+        Source::new(SourceKind::Script, "foo.ara", "function foo(): void {}"),
+        Source::new(SourceKind::Script, "bar.ara", "function bar(): void {}"),
+    ]);
+
+    let before = allocations();
+    let source = map.named("bar.ara").unwrap();
+    let after = allocations();
+
+    assert_eq!(source.name(), "bar.ara");
+    assert_eq!(before, after);
+
+    // after mutable access, the index is rebuilt once, and lookups are indexed again.
+    map.iter_mut().for_each(|source| source.content.clear());
+    assert_eq!(map.named("foo.ara").unwrap().content, "");
+
+    let before = allocations();
+    let source = map.named("bar.ara").unwrap();
+    let after = allocations();
+
+    assert_eq!(source.name(), "bar.ara");
+    assert_eq!(before, after);
+}
+
+#[test]
+fn test_missing_origin_does_not_allocate() {
+    let map = SourceMap::new(vec![Source::new(SourceKind::Script, "foo.ara", "")]);
+    assert!(map.id_of("foo.ara").is_some());
+
+    let before = allocations();
+    let missing = ["bar.ara", "foo/bar.ara", "vendor/foo.d.ara"]
+        .iter()
+        .all(|origin| map.id_of(origin).is_none());
+    let after = allocations();
+
+    assert!(missing);
+    assert_eq!(before, after);
+}